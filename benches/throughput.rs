@@ -38,7 +38,7 @@ fn load_wiki_samples() -> Vec<String> {
         .map(|entries| {
             entries
                 .filter_map(|e| e.ok())
-                .filter(|e| e.path().extension().map_or(false, |ext| ext == "html"))
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "html"))
                 .filter_map(|e| {
                     let mut html = String::new();
                     File::open(e.path())
@@ -142,7 +142,7 @@ pub fn bench_concurrent_processing(c: &mut Criterion) {
                     .build()
                     .unwrap();
 
-                let samples_clone: Vec<_> = wiki_samples.iter().cloned().collect();
+                let samples_clone: Vec<_> = wiki_samples.to_vec();
 
                 b.to_async(rt).iter(|| {
                     let samples = samples_clone.clone();
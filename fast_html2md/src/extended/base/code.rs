@@ -0,0 +1,77 @@
+/// Class prefixes naming the language of a code block.
+const LANGUAGE_PREFIXES: [&str; 3] = ["language-", "lang-", "highlight-source-"];
+
+/// Read the language of a code block from its `data-lang` attribute or from a
+/// `language-*`, `lang-*` or `highlight-source-*` class.
+pub(crate) fn code_language(class: Option<&str>, data_lang: Option<&str>) -> Option<String> {
+    data_lang.and_then(info_string).or_else(|| {
+        class?.split_ascii_whitespace().find_map(|name| {
+            LANGUAGE_PREFIXES
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix))
+                .and_then(info_string)
+        })
+    })
+}
+
+/// Keep the part of the language that is valid in a fence info string.
+fn info_string(language: &str) -> Option<String> {
+    let language: String = language
+        .trim()
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '`')
+        .collect();
+
+    (!language.is_empty()).then_some(language)
+}
+
+/// The longest run of the character in the text.
+fn longest_run(text: &str, marker: char) -> usize {
+    text.split(|c| c != marker)
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+/// The longest run of backticks in the text.
+fn longest_backtick_run(text: &str) -> usize {
+    longest_run(text, '`')
+}
+
+/// The fence of a code block, longer than any run of the fence character in the text.
+pub(crate) fn code_fence(text: &str, marker: char) -> String {
+    marker
+        .to_string()
+        .repeat((longest_run(text, marker) + 1).max(3))
+}
+
+/// The opening and closing delimiters of an inline code span. The delimiter is longer
+/// than any backtick run of the text, and padded with a space when the text starts or
+/// ends with a backtick, or is wrapped in spaces that CommonMark would strip.
+pub(crate) fn code_span_delimiters(text: &str) -> (String, String) {
+    let delimiter = "`".repeat(longest_backtick_run(text) + 1);
+    let padded = text.starts_with('`')
+        || text.ends_with('`')
+        || (text.len() > 1
+            && text.starts_with(' ')
+            && text.ends_with(' ')
+            && !text.bytes().all(|b| b == b' '));
+
+    if padded {
+        (format!("{delimiter} "), format!(" {delimiter}"))
+    } else {
+        (delimiter.clone(), delimiter)
+    }
+}
+
+/// Write the text as an inline code span.
+#[cfg(feature = "rewriter")]
+pub(crate) fn code_span(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let (open, close) = code_span_delimiters(text);
+
+    format!("{open}{text}{close}")
+}
@@ -0,0 +1,11 @@
+/// The prefix of a definition: the `: ` of PHP Markdown Extra and Pandoc, or an indent in strict CommonMark.
+///
+/// The indent is written with non-breaking spaces so the whitespace cleanup keeps it, it ends up as four spaces.
+#[inline]
+pub fn definition_prefix(commonmark: bool) -> &'static str {
+    if commonmark {
+        "\u{a0}\u{a0}\u{a0}\u{a0}"
+    } else {
+        ": "
+    }
+}
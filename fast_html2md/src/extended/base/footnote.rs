@@ -0,0 +1,114 @@
+/// The footnotes of a document: the cited notes and the citations pointing at them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Footnotes {
+    /// The id of every cited note with its label, in citation order.
+    notes: Vec<(String, String)>,
+    /// The ids of the citations, the targets of the backlinks of the notes.
+    citations: Vec<String>,
+    /// The ids of the cited notes written as footnote definitions.
+    defined: Vec<String>,
+}
+
+impl Footnotes {
+    /// Cite the note and get its label, a note cited again keeps the label of its first citation.
+    pub fn cite(&mut self, note_id: &str, citation_id: Option<String>, text: &str) -> &str {
+        if let Some(citation_id) = citation_id.filter(|id| !id.is_empty()) {
+            if !self.citations.contains(&citation_id) {
+                self.citations.push(citation_id);
+            }
+        }
+
+        let index = match self.notes.iter().position(|(id, _)| id == note_id) {
+            Some(index) => index,
+            None => {
+                let ordinal = self.notes.len() + 1;
+                let mut label = footnote_label(text).unwrap_or_else(|| ordinal.to_string());
+
+                // two notes cited with the same text, "[1]" of the notes and of the references
+                if self.notes.iter().any(|(_, known)| *known == label) {
+                    label = format!("{label}-{ordinal}");
+                }
+
+                self.notes.push((note_id.to_string(), label));
+                ordinal - 1
+            }
+        };
+
+        &self.notes[index].1
+    }
+
+    /// Write the cited note as a footnote definition and get its label.
+    pub fn define(&mut self, note_id: &str) -> Option<&str> {
+        let (id, label) = self.notes.iter().find(|(id, _)| id == note_id)?;
+
+        if !self.defined.contains(id) {
+            self.defined.push(id.clone());
+        }
+
+        Some(label)
+    }
+
+    /// The empty definitions of the cited notes missing from the document, appended at the
+    /// end on their own lines like the notes written, so no footnote is left undefined.
+    pub fn missing_definitions(&self) -> String {
+        let mut out = String::new();
+
+        for (id, label) in self.notes.iter() {
+            if !self.defined.contains(id) {
+                out.push_str(&format!("\n[^{label}]:"));
+            }
+        }
+
+        out
+    }
+
+    /// The link goes back to a citation.
+    pub fn is_backlink(&self, href: &str) -> bool {
+        note_id(href).is_some_and(|id| self.citations.iter().any(|citation| citation == id))
+    }
+}
+
+/// The id of the in-page element the link points at: `#cite_note-3` is `cite_note-3`.
+#[inline]
+pub fn note_id(href: &str) -> Option<&str> {
+    href.trim().strip_prefix('#').filter(|id| !id.is_empty())
+}
+
+/// The label of a footnote from the text of its citation: `[3]` is `3`.
+pub fn footnote_label(text: &str) -> Option<String> {
+    let text = crate::decode_text_opt(text).unwrap_or_else(|| text.to_string());
+    let text = text.trim();
+    let text = text
+        .strip_prefix("&#91;")
+        .or_else(|| text.strip_prefix('['))
+        .unwrap_or(text);
+    let text = text
+        .strip_suffix("&#93;")
+        .or_else(|| text.strip_suffix(']'))
+        .unwrap_or(text);
+
+    // the label can't hold whitespace or the characters closing it
+    let label = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .replace(['[', ']', '^', '\\'], "");
+
+    (!label.is_empty()).then_some(label)
+}
+
+/// The `<sup>` is a citation: `<sup class="reference">`.
+#[inline]
+pub fn is_reference_class(class: &str) -> bool {
+    class
+        .split_ascii_whitespace()
+        .any(|class| class == "reference")
+}
+
+/// The class of the backlinks written before the text of a note: `mw-cite-backlink`.
+#[inline]
+pub fn is_backlink_class(class: &str) -> bool {
+    class
+        .split_ascii_whitespace()
+        .any(|class| class.ends_with("cite-backlink"))
+}
@@ -0,0 +1,168 @@
+use crate::ImageSourcePolicy;
+
+/// Attributes of lazy loaded images holding the real url, the `src` is often a placeholder.
+const LAZY_SRC_ATTRIBUTES: [&str; 2] = ["data-src", "data-lazy-src"];
+
+/// Attributes holding the candidates of a responsive image.
+const SRCSET_ATTRIBUTES: [&str; 2] = ["srcset", "data-srcset"];
+
+/// A candidate of a `srcset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate<'a> {
+    /// The url of the image.
+    url: &'a str,
+    /// The `w` descriptor.
+    width: Option<u32>,
+    /// The `x` descriptor, `1x` without a descriptor.
+    density: f32,
+}
+
+/// Split a `srcset` into the url and the descriptors of its candidates.
+///
+/// As in the html parsing of `srcset`, the url runs up to the next whitespace so it may
+/// hold commas, the descriptors run up to the next comma outside parentheses.
+pub(crate) fn split_srcset(srcset: &str) -> Vec<(&str, &str)> {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+
+        if rest.is_empty() {
+            break;
+        }
+
+        let url_end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let url = &rest[..url_end];
+        rest = &rest[url_end..];
+
+        // a url ending with commas has no descriptors
+        if url.ends_with(',') {
+            candidates.push((url.trim_end_matches(','), ""));
+            continue;
+        }
+
+        let mut in_parens = false;
+        let descriptors_end = rest
+            .find(|c: char| match c {
+                '(' => {
+                    in_parens = true;
+                    false
+                }
+                ')' => {
+                    in_parens = false;
+                    false
+                }
+                ',' => !in_parens,
+                _ => false,
+            })
+            .unwrap_or(rest.len());
+
+        candidates.push((url, rest[..descriptors_end].trim()));
+        rest = &rest[descriptors_end..];
+    }
+
+    candidates
+}
+
+/// Parse the candidates of a `srcset`, candidates with an invalid descriptor are dropped.
+fn parse_srcset(srcset: &str) -> Vec<Candidate<'_>> {
+    split_srcset(srcset)
+        .into_iter()
+        .filter_map(|(url, descriptors)| {
+            let mut parts = descriptors.split_ascii_whitespace();
+            let mut candidate = Candidate {
+                url,
+                width: None,
+                density: 1.0,
+            };
+
+            match (parts.next(), parts.next()) {
+                (None, _) => (),
+                (Some(descriptor), None) => {
+                    if let Some(width) = descriptor.strip_suffix('w') {
+                        candidate.width = Some(width.parse().ok()?);
+                    } else if let Some(density) = descriptor.strip_suffix('x') {
+                        candidate.density = density.parse().ok()?;
+                    } else {
+                        return None;
+                    }
+                }
+                _ => return None,
+            }
+
+            Some(candidate)
+        })
+        .collect()
+}
+
+/// Pick the url of a `srcset` following the policy.
+pub(crate) fn select_srcset(srcset: &str, policy: ImageSourcePolicy) -> Option<&str> {
+    let candidates = parse_srcset(srcset);
+    let by_size = |a: &&Candidate, b: &&Candidate| {
+        a.width.cmp(&b.width).then(a.density.total_cmp(&b.density))
+    };
+
+    let candidate = match policy {
+        ImageSourcePolicy::First => candidates.first(),
+        ImageSourcePolicy::Largest => candidates.iter().max_by(by_size),
+        ImageSourcePolicy::MaxWidth(max_width) => candidates
+            .iter()
+            .filter(|candidate| candidate.width.is_none_or(|width| width <= max_width))
+            .max_by(by_size)
+            .or_else(|| candidates.iter().min_by(by_size)),
+    };
+
+    candidate.map(|candidate| candidate.url)
+}
+
+/// The sources of an image, from the most to the least preferred.
+#[derive(Debug, Default)]
+pub(crate) struct ImageSources {
+    /// The `srcset` of the first `<source>` of the enclosing `<picture>`.
+    pub(crate) picture: Option<String>,
+    /// The `srcset` or `data-srcset` of the image.
+    pub(crate) srcset: Option<String>,
+    /// The `data-src` or `data-lazy-src` of the image.
+    pub(crate) lazy: Option<String>,
+    /// The `src` of the image.
+    pub(crate) src: Option<String>,
+}
+
+impl ImageSources {
+    /// Read the sources from the attributes of the image.
+    pub(crate) fn from_attributes(
+        picture: Option<String>,
+        attribute: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let first = |names: &[&str]| {
+            names
+                .iter()
+                .filter_map(|name| attribute(name))
+                .find(|value| !value.trim().is_empty())
+        };
+
+        Self {
+            picture,
+            srcset: first(&SRCSET_ATTRIBUTES),
+            lazy: first(&LAZY_SRC_ATTRIBUTES),
+            src: attribute("src"),
+        }
+    }
+
+    /// Pick the url of the image: a responsive candidate, the lazy loaded url, then the `src`.
+    pub(crate) fn select(&self, policy: ImageSourcePolicy) -> Option<&str> {
+        [&self.picture, &self.srcset]
+            .into_iter()
+            .flatten()
+            .find_map(|srcset| select_srcset(srcset, policy))
+            .or_else(|| self.fallback())
+    }
+
+    /// The url of an image written as html next to its `srcset`: the lazy loaded url, then the `src`.
+    pub(crate) fn fallback(&self) -> Option<&str> {
+        self.lazy.as_deref().or(self.src.as_deref())
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+/// The link reference definitions of the document, numbered in the order the urls are first used.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LinkReferences {
+    /// The urls, the reference of a url is its position plus one.
+    urls: Vec<String>,
+    /// The reference of each url.
+    numbers: HashMap<String, usize>,
+}
+
+impl LinkReferences {
+    /// The reference of the url, repeated urls share one reference.
+    pub(crate) fn reference(&mut self, url: &str) -> usize {
+        if let Some(number) = self.numbers.get(url) {
+            return *number;
+        }
+
+        self.urls.push(url.to_string());
+        self.numbers.insert(url.to_string(), self.urls.len());
+        self.urls.len()
+    }
+
+    /// Write the definitions block appended at the end of the document.
+    pub(crate) fn definitions(&self) -> String {
+        let mut out = String::new();
+
+        if !self.urls.is_empty() {
+            out.push('\n');
+        }
+
+        for (index, url) in self.urls.iter().enumerate() {
+            out.push('\n');
+            out.push('[');
+            out.push_str(&(index + 1).to_string());
+            out.push_str("]: ");
+
+            // [CommonMark Spec](https://spec.commonmark.org/0.31.2/#link-destination)
+            if url.is_empty() || url.bytes().any(|b| b.is_ascii_control() || b == b' ') {
+                out.push('<');
+                out.push_str(url);
+                out.push('>');
+            } else {
+                out.push_str(url);
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,542 @@
+/// Functions written as operator names: `\sin`, `\log`.
+const FUNCTIONS: [&str; 32] = [
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "coth", "log", "ln", "lg", "exp", "lim", "liminf", "limsup", "max", "min", "sup", "inf", "det",
+    "dim", "ker", "deg", "gcd", "arg", "hom", "Pr",
+];
+
+/// Operators taking their under and over scripts as limits: `\sum_{i}^{n}`.
+const LIMIT_OPERATORS: [&str; 17] = [
+    "\\sum",
+    "\\prod",
+    "\\coprod",
+    "\\int",
+    "\\oint",
+    "\\bigcup",
+    "\\bigcap",
+    "\\bigoplus",
+    "\\bigotimes",
+    "\\lim",
+    "\\liminf",
+    "\\limsup",
+    "\\max",
+    "\\min",
+    "\\sup",
+    "\\inf",
+    "\\det",
+];
+
+/// A MathML element.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct MathNode {
+    /// The lowercase tag name.
+    tag: String,
+    /// The attributes of the element.
+    attributes: Vec<(String, String)>,
+    /// The text of a token element: `<mi>`, `<mn>`, `<mo>`, `<mtext>`.
+    text: String,
+    /// The child elements.
+    children: Vec<MathNode>,
+}
+
+impl MathNode {
+    /// The value of the attribute.
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first annotation holding TeX.
+    fn tex_annotation(&self) -> Option<&str> {
+        if self.tag == "annotation"
+            && self
+                .attribute("encoding")
+                .is_some_and(|encoding| encoding.to_ascii_lowercase().contains("tex"))
+        {
+            return Some(&self.text);
+        }
+
+        self.children.iter().find_map(MathNode::tex_annotation)
+    }
+}
+
+/// Build the TeX of a `<math>` element or of a MathJax `<script type="math/tex">`
+/// from the start tags, the text and the end tags of its content.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct MathBuilder {
+    /// The open elements, innermost last.
+    stack: Vec<MathNode>,
+    /// The closed root element.
+    root: Option<MathNode>,
+}
+
+impl MathBuilder {
+    /// Open an element.
+    pub(crate) fn open(&mut self, tag: &str, attributes: Vec<(String, String)>) {
+        self.stack.push(MathNode {
+            tag: tag.to_ascii_lowercase(),
+            attributes,
+            ..Default::default()
+        });
+    }
+
+    /// Add the text to the open element.
+    pub(crate) fn text(&mut self, text: &str) {
+        if let Some(node) = self.stack.last_mut() {
+            node.text.push_str(text);
+        }
+    }
+
+    /// Close the open element.
+    pub(crate) fn close(&mut self) {
+        if let Some(node) = self.stack.pop() {
+            match self.stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => self.root = Some(node),
+            }
+        }
+    }
+
+    /// The root element is closed.
+    #[cfg(feature = "rewriter")]
+    pub(crate) fn is_done(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Write the math: `$x^2$` inline or `$$x^2$$` on its own line.
+    ///
+    /// The TeX of a script or of an annotation is used as is, then the `alttext`,
+    /// then the MathML is translated.
+    pub(crate) fn into_markdown(mut self) -> Option<String> {
+        // an element left open by truncated html
+        while !self.stack.is_empty() {
+            self.close();
+        }
+
+        let root = self.root?;
+
+        let (tex, display) = match root.tag.as_str() {
+            "script" => (
+                root.text.clone(),
+                math_script_display(root.attribute("type").unwrap_or_default()).unwrap_or(false),
+            ),
+            _ => (
+                root.tex_annotation()
+                    .or_else(|| root.attribute("alttext"))
+                    .map(str::to_string)
+                    .unwrap_or_else(|| translate(&root)),
+                root.attribute("display") == Some("block")
+                    || root.attribute("mode") == Some("display"),
+            ),
+        };
+
+        let tex = clean_tex(&tex);
+
+        if tex.is_empty() {
+            None
+        } else if display {
+            Some(format!("\n$${tex}$$\n"))
+        } else {
+            Some(format!("${tex}$"))
+        }
+    }
+}
+
+/// The `type` of a MathJax script: `Some(true)` for `math/tex; mode=display`.
+pub(crate) fn math_script_display(script_type: &str) -> Option<bool> {
+    let script_type = script_type.trim().to_ascii_lowercase();
+
+    script_type
+        .starts_with("math/tex")
+        .then(|| script_type.contains("mode=display"))
+}
+
+/// The element is a rendering of the math next to its source, dropped with its content:
+/// the KaTeX html, the MathJax 2 output and the Wikipedia fallback images.
+pub(crate) fn is_math_rendering(tag_name: &str, class: Option<&str>) -> bool {
+    tag_name == "mjx-math"
+        || class.is_some_and(|class| {
+            class.split_ascii_whitespace().any(|class| {
+                class == "katex-html"
+                    || class.starts_with("mwe-math-fallback-image")
+                    // the MathJax 3 container holds the assistive MathML
+                    || (class.starts_with("MathJax") && tag_name != "mjx-container")
+            })
+        })
+}
+
+/// Trim the TeX, unwrap `{\displaystyle …}` and keep it on one line.
+fn clean_tex(tex: &str) -> String {
+    let mut tex = tex.trim();
+
+    for style in ["\\displaystyle", "\\textstyle"] {
+        if let Some(inner) = tex
+            .strip_prefix('{')
+            .and_then(|inner| inner.strip_suffix('}'))
+            .and_then(|inner| inner.trim_start().strip_prefix(style))
+        {
+            if balanced(inner) {
+                tex = inner.trim();
+            }
+        }
+    }
+
+    tex.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The braces of the TeX are balanced.
+fn balanced(tex: &str) -> bool {
+    let mut depth = 0usize;
+    let mut escaped = false;
+
+    for c in tex.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => match depth.checked_sub(1) {
+                Some(next) => depth = next,
+                None => return false,
+            },
+            _ => (),
+        }
+    }
+
+    depth == 0
+}
+
+/// Append a piece of TeX, a control word is kept apart from the letters after it.
+fn push_tex(out: &mut String, piece: &str) {
+    let control_word = out
+        .rsplit_once('\\')
+        .is_some_and(|(_, word)| !word.is_empty() && word.chars().all(|c| c.is_ascii_alphabetic()));
+
+    if control_word && piece.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        out.push(' ');
+    }
+
+    out.push_str(piece);
+}
+
+/// A single character or control word stays as is, anything else is grouped.
+fn group(tex: &str) -> String {
+    let atom = tex.chars().count() == 1
+        || tex
+            .strip_prefix('\\')
+            .is_some_and(|word| !word.is_empty() && word.chars().all(|c| c.is_ascii_alphabetic()));
+
+    if atom {
+        tex.to_string()
+    } else {
+        format!("{{{tex}}}")
+    }
+}
+
+/// The TeX of a symbol.
+fn symbol(c: char) -> Option<&'static str> {
+    Some(match c {
+        'α' => "\\alpha",
+        'β' => "\\beta",
+        'γ' => "\\gamma",
+        'δ' => "\\delta",
+        'ϵ' => "\\epsilon",
+        'ε' => "\\varepsilon",
+        'ζ' => "\\zeta",
+        'η' => "\\eta",
+        'θ' => "\\theta",
+        'ϑ' => "\\vartheta",
+        'ι' => "\\iota",
+        'κ' => "\\kappa",
+        'λ' => "\\lambda",
+        'μ' => "\\mu",
+        'ν' => "\\nu",
+        'ξ' => "\\xi",
+        'π' => "\\pi",
+        'ρ' => "\\rho",
+        'σ' => "\\sigma",
+        'ς' => "\\varsigma",
+        'τ' => "\\tau",
+        'υ' => "\\upsilon",
+        'ϕ' => "\\phi",
+        'φ' => "\\varphi",
+        'χ' => "\\chi",
+        'ψ' => "\\psi",
+        'ω' => "\\omega",
+        'Γ' => "\\Gamma",
+        'Δ' => "\\Delta",
+        'Θ' => "\\Theta",
+        'Λ' => "\\Lambda",
+        'Ξ' => "\\Xi",
+        'Π' => "\\Pi",
+        'Σ' => "\\Sigma",
+        'Υ' => "\\Upsilon",
+        'Φ' => "\\Phi",
+        'Ψ' => "\\Psi",
+        'Ω' => "\\Omega",
+        'ℝ' => "\\mathbb{R}",
+        'ℕ' => "\\mathbb{N}",
+        'ℤ' => "\\mathbb{Z}",
+        'ℚ' => "\\mathbb{Q}",
+        'ℂ' => "\\mathbb{C}",
+        'ℏ' => "\\hbar",
+        'ℓ' => "\\ell",
+        '±' => "\\pm",
+        '∓' => "\\mp",
+        '×' => "\\times",
+        '÷' => "\\div",
+        '⋅' | '·' => "\\cdot",
+        '∗' => "\\ast",
+        '∘' => "\\circ",
+        '−' => "-",
+        '≤' => "\\leq",
+        '≥' => "\\geq",
+        '≠' => "\\neq",
+        '≈' => "\\approx",
+        '≡' => "\\equiv",
+        '∼' => "\\sim",
+        '≃' => "\\simeq",
+        '≅' => "\\cong",
+        '∝' => "\\propto",
+        '≪' => "\\ll",
+        '≫' => "\\gg",
+        '→' => "\\to",
+        '←' => "\\leftarrow",
+        '↔' => "\\leftrightarrow",
+        '⇒' => "\\Rightarrow",
+        '⇐' => "\\Leftarrow",
+        '⇔' => "\\Leftrightarrow",
+        '↦' => "\\mapsto",
+        '∈' => "\\in",
+        '∉' => "\\notin",
+        '∋' => "\\ni",
+        '⊂' => "\\subset",
+        '⊃' => "\\supset",
+        '⊆' => "\\subseteq",
+        '⊇' => "\\supseteq",
+        '∪' => "\\cup",
+        '∩' => "\\cap",
+        '∅' => "\\emptyset",
+        '∀' => "\\forall",
+        '∃' => "\\exists",
+        '¬' => "\\neg",
+        '∧' => "\\land",
+        '∨' => "\\lor",
+        '⊕' => "\\oplus",
+        '⊗' => "\\otimes",
+        '⊥' => "\\perp",
+        '∥' => "\\parallel",
+        '†' => "\\dagger",
+        '∞' => "\\infty",
+        '∂' => "\\partial",
+        '∇' => "\\nabla",
+        '∑' => "\\sum",
+        '∏' => "\\prod",
+        '∐' => "\\coprod",
+        '∫' => "\\int",
+        '∮' => "\\oint",
+        '⋃' => "\\bigcup",
+        '⋂' => "\\bigcap",
+        '√' => "\\surd",
+        '…' => "\\ldots",
+        '⋯' => "\\cdots",
+        '⋮' => "\\vdots",
+        '⋱' => "\\ddots",
+        '′' => "'",
+        '″' => "''",
+        '⟨' => "\\langle",
+        '⟩' => "\\rangle",
+        '‖' => "\\|",
+        '⌊' => "\\lfloor",
+        '⌋' => "\\rfloor",
+        '⌈' => "\\lceil",
+        '⌉' => "\\rceil",
+        '°' => "^\\circ",
+        '{' => "\\{",
+        '}' => "\\}",
+        '%' => "\\%",
+        '#' => "\\#",
+        '&' => "\\&",
+        '$' => "\\$",
+        '_' => "\\_",
+        '\\' => "\\backslash",
+        // function application, invisible times and separator
+        '\u{2061}' | '\u{2062}' | '\u{2063}' | '\u{2064}' | '\u{200b}' => "",
+        _ => return None,
+    })
+}
+
+/// The TeX of the text of a token element.
+fn token_tex(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut buf = [0u8; 4];
+
+    for c in text.trim().chars() {
+        match symbol(c) {
+            Some(tex) => push_tex(&mut out, tex),
+            None => push_tex(&mut out, c.encode_utf8(&mut buf)),
+        }
+    }
+
+    out
+}
+
+/// The command of a `mathvariant`.
+fn variant_command(variant: &str) -> Option<&'static str> {
+    Some(match variant {
+        "normal" => "\\mathrm",
+        "bold" => "\\mathbf",
+        "bold-italic" => "\\boldsymbol",
+        "double-struck" => "\\mathbb",
+        "script" => "\\mathcal",
+        "fraktur" => "\\mathfrak",
+        "sans-serif" => "\\mathsf",
+        "monospace" => "\\mathtt",
+        _ => return None,
+    })
+}
+
+/// The command of an accent written over or under its base.
+fn accent_command(accent: &str, over: bool) -> Option<&'static str> {
+    Some(match (accent, over) {
+        ("¯" | "‾" | "_" | "−" | "-", true) => "\\overline",
+        ("^" | "ˆ", true) => "\\hat",
+        ("~" | "˜", true) => "\\tilde",
+        ("→" | "⃗", true) => "\\vec",
+        ("˙" | ".", true) => "\\dot",
+        ("¨", true) => "\\ddot",
+        ("ˇ", true) => "\\check",
+        ("˘", true) => "\\breve",
+        ("⏞", true) => "\\overbrace",
+        ("_" | "¯" | "‾" | "−" | "-", false) => "\\underline",
+        ("⏟", false) => "\\underbrace",
+        _ => return None,
+    })
+}
+
+/// Translate the children of the element one after the other.
+fn translate_children(children: &[MathNode]) -> String {
+    let mut out = String::new();
+
+    for child in children {
+        push_tex(&mut out, &translate(child));
+    }
+
+    out
+}
+
+/// Translate a MathML element to TeX.
+fn translate(node: &MathNode) -> String {
+    let child = |index: usize| node.children.get(index).map(translate).unwrap_or_default();
+
+    match node.tag.as_str() {
+        "mi" => {
+            let text = node.text.trim();
+            let variant = node.attribute("mathvariant").and_then(variant_command);
+
+            if FUNCTIONS.contains(&text) {
+                format!("\\{text}")
+            } else if text.chars().count() > 1 && text.chars().all(char::is_alphabetic) {
+                format!("{}{{{text}}}", variant.unwrap_or("\\mathrm"))
+            } else {
+                match variant {
+                    Some(command) => format!("{command}{{{}}}", token_tex(text)),
+                    None => token_tex(text),
+                }
+            }
+        }
+        "mn" | "mo" => {
+            let text = node.text.trim();
+
+            if FUNCTIONS.contains(&text) {
+                format!("\\{text}")
+            } else {
+                token_tex(text)
+            }
+        }
+        "mtext" | "ms" => {
+            let text = node.text.trim();
+
+            if text.is_empty() {
+                String::new()
+            } else {
+                format!("\\text{{{}}}", token_tex(text))
+            }
+        }
+        "mspace" => " ".into(),
+        "msup" => format!("{}^{}", group(&child(0)), group(&child(1))),
+        "msub" => format!("{}_{}", group(&child(0)), group(&child(1))),
+        "msubsup" => format!(
+            "{}_{}^{}",
+            group(&child(0)),
+            group(&child(1)),
+            group(&child(2))
+        ),
+        "mfrac" => format!("\\frac{{{}}}{{{}}}", child(0), child(1)),
+        "msqrt" => format!("\\sqrt{{{}}}", translate_children(&node.children)),
+        "mroot" => format!("\\sqrt[{}]{{{}}}", child(1), child(0)),
+        tag @ ("munder" | "mover" | "munderover") => {
+            let base = child(0);
+            let limits = LIMIT_OPERATORS.contains(&base.as_str());
+            let over = tag == "mover";
+            let script = child(1);
+            let accent = node
+                .children
+                .get(1)
+                .and_then(|script| accent_command(script.text.trim(), over));
+
+            match (tag, accent) {
+                ("munderover", _) if limits => {
+                    format!("{base}_{}^{}", group(&script), group(&child(2)))
+                }
+                ("munderover", _) => format!(
+                    "\\underset{{{script}}}{{\\overset{{{}}}{{{base}}}}}",
+                    child(2)
+                ),
+                (_, Some(command)) => format!("{command}{{{base}}}"),
+                ("munder", None) if limits => format!("{base}_{}", group(&script)),
+                ("mover", None) if limits => format!("{base}^{}", group(&script)),
+                ("munder", None) => format!("\\underset{{{script}}}{{{base}}}"),
+                _ => format!("\\overset{{{script}}}{{{base}}}"),
+            }
+        }
+        "mfenced" => {
+            let open = node.attribute("open").unwrap_or("(");
+            let close = node.attribute("close").unwrap_or(")");
+            let separator = node
+                .attribute("separators")
+                .and_then(|separators| separators.trim().chars().next())
+                .unwrap_or(',');
+
+            let children: Vec<String> = node.children.iter().map(translate).collect();
+
+            format!(
+                "{}{}{}",
+                token_tex(open),
+                children.join(&separator.to_string()),
+                token_tex(close)
+            )
+        }
+        "mtable" => {
+            let rows: Vec<String> = node
+                .children
+                .iter()
+                .map(|row| {
+                    // the label of a numbered row is its first cell
+                    let cells = match row.tag.as_str() {
+                        "mlabeledtr" => row.children.get(1..).unwrap_or_default(),
+                        _ => &row.children[..],
+                    };
+
+                    cells.iter().map(translate).collect::<Vec<_>>().join(" & ")
+                })
+                .collect();
+
+            format!("\\begin{{matrix}}{}\\end{{matrix}}", rows.join(" \\\\ "))
+        }
+        "semantics" => child(0),
+        "annotation" | "annotation-xml" | "none" | "mprescripts" => String::new(),
+        _ => translate_children(&node.children),
+    }
+}
@@ -1 +1,11 @@
+pub mod code;
+pub mod definition;
+pub mod entity;
+pub mod footnote;
 pub mod iframe;
+pub mod image;
+pub mod link;
+pub mod math;
+pub mod table;
+pub mod task;
+pub mod url;
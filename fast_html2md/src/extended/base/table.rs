@@ -0,0 +1,204 @@
+/// The alignment of a table column.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Alignment {
+    /// No alignment, the renderer default.
+    #[default]
+    None,
+    /// `:---`
+    Left,
+    /// `:---:`
+    Center,
+    /// `---:`
+    Right,
+}
+
+impl Alignment {
+    /// Read the alignment of a cell, `col` or `colgroup` from its `align` and `style` attributes.
+    /// A `text-align` declaration wins over the legacy `align` attribute.
+    pub(crate) fn from_attributes(align: Option<&str>, style: Option<&str>) -> Self {
+        let from_style = style
+            .and_then(|style| {
+                style.split(';').rev().find_map(|declaration| {
+                    let (property, value) = declaration.split_once(':')?;
+                    property
+                        .trim()
+                        .eq_ignore_ascii_case("text-align")
+                        .then(|| Self::from_keyword(value))
+                })
+            })
+            .unwrap_or_default();
+
+        match from_style {
+            Self::None => align.map(Self::from_keyword).unwrap_or_default(),
+            alignment => alignment,
+        }
+    }
+
+    /// Map a css or html alignment keyword.
+    fn from_keyword(value: &str) -> Self {
+        let value = value.trim();
+        let value = value
+            .strip_suffix("!important")
+            .map(str::trim_end)
+            .unwrap_or(value);
+
+        if value.eq_ignore_ascii_case("left") || value.eq_ignore_ascii_case("start") {
+            Self::Left
+        } else if value.eq_ignore_ascii_case("center") {
+            Self::Center
+        } else if value.eq_ignore_ascii_case("right") || value.eq_ignore_ascii_case("end") {
+            Self::Right
+        } else {
+            Self::None
+        }
+    }
+
+    /// The number of colons written by the delimiter.
+    fn colons(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Left | Self::Right => 1,
+            Self::Center => 2,
+        }
+    }
+
+    /// Write the delimiter cell of a column `width` characters wide, without the `|` separators.
+    /// The colons are part of the width, at least one dash is written: `---`, `:--`, `:-:`.
+    pub(crate) fn push_delimiter(self, out: &mut String, width: usize) {
+        if matches!(self, Self::Left | Self::Center) {
+            out.push(':');
+        }
+
+        for _ in 0..width.saturating_sub(self.colons()).max(1) {
+            out.push('-');
+        }
+
+        if matches!(self, Self::Right | Self::Center) {
+            out.push(':');
+        }
+    }
+}
+
+/// The alignments of the columns declared by a `<colgroup>` and its `<col>` children.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ColumnGroup {
+    /// The alignment of the group, inherited by its columns.
+    alignment: Alignment,
+    /// The `span` of the group, used when it has no `<col>` children.
+    span: usize,
+    /// The group declared `<col>` children.
+    has_cols: bool,
+}
+
+impl ColumnGroup {
+    /// A new column group.
+    pub(crate) fn new(alignment: Alignment, span: usize) -> Self {
+        Self {
+            alignment,
+            span,
+            has_cols: false,
+        }
+    }
+
+    /// Add a `<col>` of the group to the column alignments.
+    pub(crate) fn push_col(
+        &mut self,
+        columns: &mut Vec<Alignment>,
+        alignment: Alignment,
+        span: usize,
+    ) {
+        self.has_cols = true;
+
+        let alignment = match alignment {
+            Alignment::None => self.alignment,
+            alignment => alignment,
+        };
+
+        columns.extend(std::iter::repeat_n(alignment, span));
+    }
+
+    /// Close the group, a group without `<col>` children covers `span` columns.
+    pub(crate) fn finish(self, columns: &mut Vec<Alignment>) {
+        if !self.has_cols {
+            columns.extend(std::iter::repeat_n(self.alignment, self.span));
+        }
+    }
+}
+
+/// Parse a `span` or `colspan` attribute value.
+#[inline]
+pub(crate) fn parse_span(value: Option<&str>) -> usize {
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .clamp(1, 1000)
+}
+
+/// The alignment of every column: the header cell first, then the `<col>` declarations.
+pub(crate) fn resolve_alignments(
+    header: &[Alignment],
+    columns: &[Alignment],
+    count: usize,
+) -> Vec<Alignment> {
+    (0..count)
+        .map(
+            |index| match header.get(index).copied().unwrap_or_default() {
+                Alignment::None => columns.get(index).copied().unwrap_or_default(),
+                alignment => alignment,
+            },
+        )
+        .collect()
+}
+
+/// A cell of a previous row that covers a column through its `rowspan`.
+#[derive(Debug, Default, Clone)]
+struct RowSpan {
+    /// The rows still covered below the current one.
+    rows: usize,
+    /// The text of the spanning cell.
+    text: String,
+}
+
+/// The `rowspan` cells covering the columns of the following rows.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RowSpans {
+    /// The spans by column.
+    columns: Vec<RowSpan>,
+}
+
+impl RowSpans {
+    /// Take the cell covering the column of the current row, the text of the spanning cell is returned.
+    pub(crate) fn take(&mut self, column: usize) -> Option<&str> {
+        match self.columns.get_mut(column) {
+            Some(span) if span.rows > 0 => {
+                span.rows -= 1;
+                Some(&span.text)
+            }
+            _ => None,
+        }
+    }
+
+    /// Register a cell spanning `columns` columns from `column` over `rows` rows, the current one included.
+    pub(crate) fn insert(&mut self, column: usize, columns: usize, rows: usize, text: &str) {
+        if rows <= 1 {
+            return;
+        }
+
+        let end = column + columns;
+
+        if self.columns.len() < end {
+            self.columns.resize_with(end, RowSpan::default);
+        }
+
+        for span in &mut self.columns[column..end] {
+            span.rows = rows - 1;
+            span.text.clear();
+            span.text.push_str(text);
+        }
+    }
+
+    /// The number of columns that have been covered by a span.
+    pub(crate) fn len(&self) -> usize {
+        self.columns.len()
+    }
+}
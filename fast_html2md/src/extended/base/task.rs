@@ -0,0 +1,13 @@
+/// The marker of a checkbox: `[x]` checked or `[ ]`, a GFM task at the start of a list item.
+pub(crate) fn checkbox_marker(checked: bool) -> &'static str {
+    if checked {
+        "[x]"
+    } else {
+        "[ ]"
+    }
+}
+
+/// The `type` of the `<input>` is a checkbox.
+pub(crate) fn is_checkbox(input_type: Option<&str>) -> bool {
+    input_type.is_some_and(|input_type| input_type.trim().eq_ignore_ascii_case("checkbox"))
+}
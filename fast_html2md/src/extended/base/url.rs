@@ -0,0 +1,47 @@
+use super::image::split_srcset;
+use std::borrow::Cow;
+use url::Url;
+
+/// Resolve a reference against the base url: relative paths, `?query` and
+/// `//host` forms included. Absolute urls, in page `#fragments` and empty
+/// references are kept as written.
+pub(crate) fn resolve_url<'a>(base: Option<&Url>, reference: Cow<'a, str>) -> Cow<'a, str> {
+    match base {
+        Some(base)
+            if !reference.is_empty()
+                && !reference.starts_with('#')
+                && Url::parse(&reference).is_err() =>
+        {
+            base.join(&reference)
+                .map_or(reference, |url| Cow::Owned(url.to_string()))
+        }
+        _ => reference,
+    }
+}
+
+/// The base url declared by `<base href>`, resolved against the url of the options.
+pub(crate) fn document_base_url(url: Option<&Url>, href: &str) -> Option<Url> {
+    let href = href.trim();
+
+    match url {
+        Some(url) => url.join(href).ok(),
+        None => Url::parse(href).ok(),
+    }
+}
+
+/// Resolve every candidate url of a `srcset` against the base url, keeping the descriptors.
+pub(crate) fn resolve_srcset(base: Option<&Url>, srcset: &str) -> String {
+    split_srcset(srcset)
+        .into_iter()
+        .map(|(src, descriptors)| {
+            let src = resolve_url(base, Cow::Borrowed(src));
+
+            if descriptors.is_empty() {
+                src.into_owned()
+            } else {
+                format!("{src} {descriptors}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
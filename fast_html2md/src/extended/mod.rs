@@ -1,3 +1,4 @@
 // initial source from /JumperBot/whitespace-sifter
 pub mod base;
 pub mod sifter;
+pub mod wrap;
@@ -31,6 +31,7 @@ pub trait WhitespaceSifter: AsRef<str> {
         let mut ind: usize = 0;
 
         while ind < bytes.len() {
+            sift_blank_line(bytes, ind, &mut out);
             sift_preallocated_until_newline(bytes, &mut ind, &mut out);
         }
 
@@ -38,7 +39,8 @@ pub trait WhitespaceSifter: AsRef<str> {
         if out.ends_with("\r\n") {
             let _ = out.pop();
             let _ = out.pop();
-        } else if out.ends_with('\n') {
+        }
+        while out.ends_with('\n') {
             let _ = out.pop();
         }
 
@@ -69,6 +71,37 @@ pub trait WhitespaceSifterBytes: AsRef<[u8]> {
         let mut ind: usize = 0;
 
         while ind < bytes.len() {
+            sift_blank_line(bytes, ind, &mut out);
+            sift_preallocated_until_newline(bytes, &mut ind, &mut out);
+        }
+
+        // Drop trailing newline(s)
+        if out.ends_with("\r\n") {
+            let _ = out.pop();
+            let _ = out.pop();
+        }
+        while out.ends_with('\n') {
+            let _ = out.pop();
+        }
+
+        out
+    }
+
+    /// Same as [`WhitespaceSifterBytes::sift_bytes_preserve_newlines`] but keeps the
+    /// leading spaces of nested list items: "  * Text", "   1. Text".
+    /// Fenced code blocks are copied byte for byte.
+    #[must_use]
+    fn sift_bytes_preserve_list_indent(&self) -> String {
+        let bytes = self.as_ref();
+        let mut out = String::with_capacity(bytes.len());
+        let mut ind: usize = 0;
+
+        while ind < bytes.len() {
+            sift_blank_line(bytes, ind, &mut out);
+            if sift_code_fence(bytes, &mut ind, &mut out) {
+                continue;
+            }
+            sift_list_indent(bytes, &mut ind, &mut out);
             sift_preallocated_until_newline(bytes, &mut ind, &mut out);
         }
 
@@ -76,7 +109,8 @@ pub trait WhitespaceSifterBytes: AsRef<[u8]> {
         if out.ends_with("\r\n") {
             let _ = out.pop();
             let _ = out.pop();
-        } else if out.ends_with('\n') {
+        }
+        while out.ends_with('\n') {
             let _ = out.pop();
         }
 
@@ -107,6 +141,162 @@ fn sift_trim_start(bytes: &[u8], ind: &mut usize, out: &mut String) {
     }
 }
 
+/// A thematic break: three or more `-`, `*` or `_`, spaces between them.
+#[inline]
+fn is_thematic_break(line: &[u8]) -> bool {
+    let mut marks = line.iter().filter(|b| !is_ascii_whitespace(**b));
+
+    match marks.next() {
+        Some(&c @ (b'-' | b'*' | b'_')) => marks.clone().all(|b| *b == c) && marks.count() >= 2,
+        _ => false,
+    }
+}
+
+/// The underline of a setext heading: a run of `=` or `-`.
+#[inline]
+fn is_setext_underline(line: &[u8]) -> bool {
+    let line = line.trim_ascii();
+
+    matches!(line.first(), Some(&c @ (b'=' | b'-')) if line.iter().all(|b| *b == c))
+}
+
+/// A footnote definition: `[^3]: Text`.
+#[inline]
+fn is_footnote_definition(line: &[u8]) -> bool {
+    line.starts_with(b"[^") && line.windows(2).any(|pair| pair == b"]:")
+}
+
+/// Keep one blank line of the input before and after a thematic break and before a setext
+/// heading, the paragraph before them would be underlined otherwise. The blank line after
+/// a footnote definition is kept too, the text after it would continue the note.
+/// The other blank lines are dropped by `sift_trim_start`.
+fn sift_blank_line(bytes: &[u8], ind: usize, out: &mut String) {
+    if !out.ends_with('\n') || out.ends_with("\n\n") {
+        return;
+    }
+
+    let start = ind
+        + bytes[ind..]
+            .iter()
+            .take_while(|b| is_ascii_whitespace(**b))
+            .count();
+
+    // the newline of the line written is already read
+    if start == bytes.len() || !bytes[ind..start].contains(&LINE_FEED) {
+        return;
+    }
+
+    let written = &out.as_bytes()[..out.len() - 1];
+    let line_start = written
+        .iter()
+        .rposition(|b| *b == LINE_FEED)
+        .map_or(0, |p| p + 1);
+    let previous = &written[line_start..];
+    let next_end = line_end(bytes, start);
+    let next = &bytes[start..next_end];
+    let underline = &bytes[next_end..line_end(bytes, next_end)];
+
+    if is_thematic_break(previous)
+        || is_thematic_break(next)
+        || is_setext_underline(underline)
+        || (is_footnote_definition(previous) && !is_footnote_definition(next))
+    {
+        out.push('\n');
+    }
+}
+
+/// Keep the leading spaces of a line when they indent a list marker.
+/// Blank lines before it are dropped the same way `sift_trim_start` would.
+fn sift_list_indent(bytes: &[u8], ind: &mut usize, out: &mut String) {
+    let mut start = *ind;
+    let mut end = start;
+
+    while end < bytes.len() && is_ascii_whitespace(bytes[end]) {
+        if is_newline(bytes[end]) {
+            start = end + 1;
+        }
+        end += 1;
+    }
+
+    if end > start && bytes[start..end].iter().all(|b| *b == SPACE) && is_list_marker(&bytes[end..])
+    {
+        out.extend(std::iter::repeat_n(' ', end - start));
+        *ind = end;
+    }
+}
+
+/// The length of the code fence opening the bytes: three or more backticks or tildes.
+#[inline]
+fn code_fence_len(bytes: &[u8]) -> Option<(u8, usize)> {
+    let fence = match bytes.first() {
+        Some(&c @ (b'`' | b'~')) => c,
+        _ => return None,
+    };
+    let len = bytes.iter().take_while(|b| **b == fence).count();
+
+    (len >= 3).then_some((fence, len))
+}
+
+/// The end of the line starting at `start`, past its newline.
+#[inline]
+fn line_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|b| *b == LINE_FEED)
+        .map_or(bytes.len(), |p| start + p + 1)
+}
+
+/// Copy a fenced code block byte for byte, up to and including its closing fence.
+/// Blank lines before it are dropped the same way `sift_trim_start` would.
+fn sift_code_fence(bytes: &[u8], ind: &mut usize, out: &mut String) -> bool {
+    let start = *ind
+        + bytes[*ind..]
+            .iter()
+            .take_while(|b| is_ascii_whitespace(**b))
+            .count();
+
+    let Some((fence, fence_len)) = code_fence_len(&bytes[start..]) else {
+        return false;
+    };
+
+    let mut end = line_end(bytes, start);
+
+    while end < bytes.len() {
+        let line = &bytes[end..line_end(bytes, end)];
+        end += line.len();
+
+        let indent = line.iter().take_while(|b| **b == SPACE).count();
+        let closing = indent < 4
+            && matches!(code_fence_len(&line[indent..]), Some((c, len)) if c == fence && len >= fence_len)
+            && line[indent..]
+                .iter()
+                .skip_while(|b| **b == fence)
+                .all(|b| is_ascii_whitespace(*b));
+
+        if closing {
+            break;
+        }
+    }
+
+    out.push_str(&String::from_utf8_lossy(&bytes[start..end]));
+    *ind = end;
+
+    true
+}
+
+/// The bytes start with a markdown list marker followed by a space.
+#[inline]
+fn is_list_marker(bytes: &[u8]) -> bool {
+    match bytes {
+        [b'*' | b'-' | b'+', SPACE, ..] => true,
+        _ => {
+            let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+
+            digits > 0 && matches!(bytes[digits..], [b'.' | b')', SPACE, ..])
+        }
+    }
+}
+
 /// A custom implementation for `str::trim_end` (removes one trailing ASCII space if pending).
 fn sift_trim_end(out: &mut String, is_last_whitespace: bool) {
     if is_last_whitespace {
@@ -252,7 +442,6 @@ fn sift_preallocated_until_newline(bytes: &[u8], ind: &mut usize, out: &mut Stri
                         // Drop trailing ASCII space before newline (fixes " \n").
                         if is_last_whitespace {
                             out.pop();
-                            is_last_whitespace = false;
                         }
 
                         if is_last_carriage_return {
@@ -0,0 +1,306 @@
+/// An open fenced code block: the fence character and the length of the fence.
+type Fence = (u8, usize);
+
+/// A line split into its container prefix and its content.
+struct Line<'a> {
+    /// The blockquote markers of the line.
+    quote: &'a str,
+    /// The spaces before the content or the list marker.
+    indent: usize,
+    /// The prefix of the first line: the blockquote markers, the indent and the marker.
+    prefix: &'a str,
+    /// The indent of the continuation lines under the marker.
+    hanging: usize,
+    /// The line starts a list item, a footnote or a definition.
+    item: bool,
+    /// The text after the prefix.
+    content: &'a str,
+}
+
+impl<'a> Line<'a> {
+    /// Split the blockquote markers, the indent and the list marker of the line.
+    fn parse(line: &'a str) -> Self {
+        let bytes = line.as_bytes();
+        let mut quote_end = 0;
+
+        loop {
+            let start = quote_end + count_spaces(&line[quote_end..]);
+
+            if bytes.get(start) != Some(&b'>') {
+                break;
+            }
+
+            quote_end = start + 1;
+
+            if bytes.get(quote_end) == Some(&b' ') {
+                quote_end += 1;
+            }
+        }
+
+        let indent = count_spaces(&line[quote_end..]);
+        let start = quote_end + indent;
+        let marker = marker_len(&line[start..]);
+        let hanging = match marker {
+            Some(_) if line[start..].starts_with("[^") => 4,
+            Some(len) => indent + len,
+            None => indent,
+        };
+        let prefix_end = start + marker.unwrap_or_default();
+
+        Self {
+            quote: &line[..quote_end],
+            indent,
+            prefix: &line[..prefix_end],
+            hanging,
+            item: marker.is_some(),
+            content: &line[prefix_end..],
+        }
+    }
+
+    /// The prefix of the continuation lines.
+    fn continuation(&self) -> String {
+        format!("{}{:width$}", self.quote, "", width = self.hanging)
+    }
+}
+
+/// The number of leading spaces.
+#[inline]
+fn count_spaces(text: &str) -> usize {
+    text.bytes().take_while(|b| *b == b' ').count()
+}
+
+/// The length of the list item, footnote or definition marker starting the text.
+fn marker_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+
+    match bytes.first()? {
+        b'*' | b'-' | b'+' | b':' if bytes.get(1) == Some(&b' ') => Some(2),
+        b'0'..=b'9' => {
+            let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+
+            (digits <= 9
+                && matches!(bytes.get(digits), Some(b'.' | b')'))
+                && bytes.get(digits + 1) == Some(&b' '))
+            .then_some(digits + 2)
+        }
+        b'[' if text.starts_with("[^") => text.find("]: ").map(|end| end + 3),
+        _ => None,
+    }
+}
+
+/// The fence opening a code block.
+fn fence_open(content: &str) -> Option<Fence> {
+    let marker = *content.as_bytes().first()?;
+
+    if !matches!(marker, b'`' | b'~') {
+        return None;
+    }
+
+    let len = content.bytes().take_while(|b| *b == marker).count();
+
+    (len >= 3).then_some((marker, len))
+}
+
+/// The line closes the fenced code block.
+fn fence_close(content: &str, (marker, len): Fence) -> bool {
+    let content = content.trim();
+
+    content.len() >= len && content.bytes().all(|b| b == marker)
+}
+
+/// The line is a setext heading underline or a thematic break.
+fn is_underline(content: &str) -> bool {
+    let content = content.trim_end();
+
+    !content.is_empty() && content.bytes().all(|b| matches!(b, b'=' | b'-'))
+}
+
+/// The content is kept on its line: headings, table rows, html and display math.
+fn is_verbatim(content: &str) -> bool {
+    content.starts_with(['#', '|', '<']) || content.starts_with("$$") || is_underline(content)
+}
+
+/// The word would start a block when it begins a line: a heading, a quote, a list
+/// marker, a thematic break, a fence or html.
+fn starts_block(word: &str) -> bool {
+    let bytes = word.as_bytes();
+
+    match bytes.first() {
+        Some(b'>' | b'<') => true,
+        Some(b'#') => word.bytes().all(|b| b == b'#'),
+        Some(b'`' | b'~') => fence_open(word).is_some(),
+        Some(b'$') => word.starts_with("$$"),
+        Some(b'0'..=b'9') => {
+            let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+
+            digits <= 9 && digits + 1 == bytes.len() && matches!(bytes[digits], b'.' | b')')
+        }
+        Some(_) => word
+            .bytes()
+            .all(|b| matches!(b, b'*' | b'-' | b'+' | b'=' | b'_')),
+        None => false,
+    }
+}
+
+/// The end of the inline code span opened by the backtick run at `start`.
+fn code_span_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let run = bytes[start..].iter().take_while(|b| **b == b'`').count();
+    let mut index = start + run;
+
+    while index < bytes.len() {
+        if bytes[index] == b'`' {
+            let len = bytes[index..].iter().take_while(|b| **b == b'`').count();
+
+            if len == run {
+                return Some(index + len);
+            }
+
+            index += len;
+        } else {
+            index += 1;
+        }
+    }
+
+    None
+}
+
+/// The end of the link destination opened by the parenthesis at `start`.
+fn destination_end(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut index = start;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return index + 1;
+                }
+            }
+            _ => (),
+        }
+
+        index += 1;
+    }
+
+    bytes.len()
+}
+
+/// Split the text into words at the spaces outside code spans and link destinations.
+fn split_words(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'`' => match code_span_end(bytes, index) {
+                Some(end) => index = end,
+                None => index += bytes[index..].iter().take_while(|b| **b == b'`').count(),
+            },
+            b']' if bytes.get(index + 1) == Some(&b'(') => {
+                index = destination_end(bytes, index + 1);
+            }
+            b' ' => {
+                if index > start {
+                    words.push(&text[start..index]);
+                }
+                index += 1;
+                start = index;
+            }
+            _ => index += 1,
+        }
+    }
+
+    if start < bytes.len() {
+        words.push(&text[start..]);
+    }
+
+    words
+}
+
+/// Fill the words of the line up to the width, the continuation lines are indented under the prefix.
+fn wrap_line(line: &Line, width: usize, out: &mut String) {
+    let continuation = line.continuation();
+    let continuation_len = continuation.chars().count();
+    let mut len = line.prefix.chars().count();
+    let mut empty = true;
+
+    out.push_str(line.prefix);
+
+    for word in split_words(line.content) {
+        let word_len = word.chars().count();
+
+        if empty {
+            empty = false;
+        } else if len + 1 + word_len > width && !starts_block(word) {
+            out.push('\n');
+            out.push_str(&continuation);
+            len = continuation_len;
+        } else {
+            out.push(' ');
+            len += 1;
+        }
+
+        out.push_str(word);
+        len += word_len;
+    }
+}
+
+/// Hard-wrap the paragraphs and list items of the markdown at the width.
+///
+/// The blockquote and list prefixes are repeated on the continuation lines. Fenced code,
+/// tables, headings, inline code and link destinations are never broken.
+pub fn wrap_markdown(input: &str, width: usize) -> String {
+    let mut out = String::with_capacity(input.len() + input.len() / width.max(1));
+    let mut fence: Option<Fence> = None;
+    let mut in_list = false;
+    let mut lines = input.split('\n').peekable();
+
+    while let Some(text) = lines.next() {
+        let line = Line::parse(text);
+        let rest = &text[line.quote.len()..];
+
+        if let Some(open) = fence {
+            if fence_close(rest, open) {
+                fence = None;
+            }
+            out.push_str(text);
+        } else if let Some(open) = fence_open(rest.trim_start()).or(fence_open(line.content)) {
+            fence = Some(open);
+            out.push_str(text);
+        } else {
+            if line.item {
+                in_list = true;
+            } else if line.indent == 0 && !rest.trim().is_empty() {
+                in_list = false;
+            }
+
+            let indented_code = line.indent >= 4 && !in_list;
+            let heading = lines
+                .peek()
+                .is_some_and(|next| is_underline(Line::parse(next).content));
+
+            if text.chars().count() <= width
+                || indented_code
+                || heading
+                || is_verbatim(line.content)
+            {
+                out.push_str(text);
+            } else {
+                wrap_line(&line, width, &mut out);
+            }
+        }
+
+        if lines.peek().is_some() {
+            out.push('\n');
+        }
+    }
+
+    out
+}
@@ -1,19 +1,30 @@
+use extended::base::entity::{named_entity, LONGEST_NAMED_ENTITY};
 use extended::sifter::{WhitespaceSifter, WhitespaceSifterBytes};
 
 // we want to just use the rewriter instead for v0.1.
 pub mod extended;
+pub mod options;
+
+pub use options::{
+    BreakMarker, BulletMarker, ConversionOptions, EmphasisMarker, FenceMarker, HeadingStyle,
+    ImageSourcePolicy, LinkStyle, MarkdownStyle, SpanPolicy,
+};
 
 #[cfg(feature = "scraper")]
 pub use markup5ever_rcdom::{Handle, NodeData, RcDom};
 
 #[cfg(feature = "rewriter")]
 pub mod rewriter;
+#[cfg(feature = "rewriter")]
+pub use rewriter::custom::{CustomElement, CustomHandler, CustomHandlers};
+#[cfg(feature = "rewriter")]
+pub use rewriter::extract::{ConversionOutput, ExtractedImage, ExtractedLink};
 #[cfg(feature = "scraper")]
 pub mod scraper;
 #[cfg(feature = "scraper")]
 pub use scraper::{
     ignore, parse_html, parse_html_custom, parse_html_custom_base, parse_html_custom_with_url,
-    parse_html_extended,
+    parse_html_extended, parse_html_with_options,
 };
 
 // Regex patterns only needed for the scraper feature
@@ -29,7 +40,29 @@ lazy_static::lazy_static! {
 /// `html` is source HTML as `String`
 #[cfg(feature = "rewriter")]
 pub fn rewrite_html(html: &str, commonmark: bool) -> String {
-    rewriter::writer::convert_html_to_markdown(html, &None, commonmark, &None).unwrap_or_default()
+    rewrite_html_with_options(
+        html,
+        &ConversionOptions::default().with_commonmark(commonmark),
+    )
+}
+
+/// Rewrites incoming HTML into Markdown using the conversion options.
+/// # Arguments
+/// `html` is source HTML as `String`
+/// `options` controls the markdown output
+#[cfg(feature = "rewriter")]
+pub fn rewrite_html_with_options(html: &str, options: &ConversionOptions) -> String {
+    rewriter::writer::convert_html_to_markdown(html, options).unwrap_or_default()
+}
+
+/// Rewrites incoming HTML into Markdown using the conversion options, collecting the
+/// outgoing links and images of the document in the same pass.
+/// # Arguments
+/// `html` is source HTML as `String`
+/// `options` controls the markdown output, its url decides which links are internal
+#[cfg(feature = "rewriter")]
+pub fn rewrite_html_with_links(html: &str, options: &ConversionOptions) -> ConversionOutput {
+    rewriter::writer::convert_html_to_markdown_with_links(html, options).unwrap_or_default()
 }
 
 /// Main function of this library async streaming. Rewrites incoming HTML, converts it into Markdown
@@ -38,7 +71,39 @@ pub fn rewrite_html(html: &str, commonmark: bool) -> String {
 /// `html` is source HTML as `String`
 #[cfg(all(feature = "stream", feature = "rewriter"))]
 pub async fn rewrite_html_streaming(html: &str, commonmark: bool) -> String {
-    rewriter::writer::convert_html_to_markdown_send(html, &None, commonmark, &None)
+    rewrite_html_streaming_with_options(
+        html,
+        &ConversionOptions::default().with_commonmark(commonmark),
+    )
+    .await
+}
+
+/// Rewrites incoming HTML into Markdown async streaming using the conversion options.
+/// The html is written to the rewriter in chunks of `options.chunk_size`.
+/// # Arguments
+/// `html` is source HTML as `String`
+/// `options` controls the markdown output
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+pub async fn rewrite_html_streaming_with_options(
+    html: &str,
+    options: &ConversionOptions,
+) -> String {
+    rewriter::writer::convert_html_to_markdown_send_with_options(html, options)
+        .await
+        .unwrap_or_default()
+}
+
+/// Rewrites incoming HTML into Markdown async streaming using the conversion options,
+/// collecting the outgoing links and images of the document in the same pass.
+/// # Arguments
+/// `html` is source HTML as `String`
+/// `options` controls the markdown output, its url decides which links are internal
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+pub async fn rewrite_html_streaming_with_links(
+    html: &str,
+    options: &ConversionOptions,
+) -> ConversionOutput {
+    rewriter::writer::convert_html_to_markdown_with_links_send(html, options)
         .await
         .unwrap_or_default()
 }
@@ -58,7 +123,10 @@ pub fn rewrite_html_custom_with_url(
     commonmark: bool,
     url: &Option<url::Url>,
 ) -> String {
-    rewriter::writer::convert_html_to_markdown(html, &custom, commonmark, url).unwrap_or_default()
+    rewrite_html_with_options(
+        html,
+        &ConversionOptions::from_parts(custom, commonmark, url),
+    )
 }
 
 /// Custom variant of rewrite function.
@@ -78,11 +146,10 @@ pub async fn rewrite_html_custom_with_url_and_chunk(
     url: &Option<url::Url>,
     chunk_size: usize,
 ) -> String {
-    rewriter::writer::convert_html_to_markdown_send_with_size(
-        html, &custom, commonmark, url, chunk_size,
-    )
-    .await
-    .unwrap_or_default()
+    let options =
+        ConversionOptions::from_parts(custom, commonmark, url).with_chunk_size(chunk_size);
+
+    rewrite_html_streaming_with_options(html, &options).await
 }
 
 /// Custom variant of rewrite function streaming async.
@@ -100,9 +167,11 @@ pub async fn rewrite_html_custom_with_url_streaming(
     commonmark: bool,
     url: &Option<url::Url>,
 ) -> String {
-    rewriter::writer::convert_html_to_markdown_send(html, &custom, commonmark, url)
-        .await
-        .unwrap_or_default()
+    rewrite_html_streaming_with_options(
+        html,
+        &ConversionOptions::from_parts(custom, commonmark, url),
+    )
+    .await
 }
 
 /// Re-export the stream error type.
@@ -127,7 +196,11 @@ where
     S: futures_util::Stream<Item = Result<B, E>> + Unpin,
     B: AsRef<[u8]>,
 {
-    rewriter::writer::convert_html_stream_to_markdown(stream, &None, commonmark, &None).await
+    rewrite_html_stream_with_options(
+        stream,
+        &ConversionOptions::default().with_commonmark(commonmark),
+    )
+    .await
 }
 
 /// Convert an async stream of HTML byte chunks into markdown with custom options.
@@ -148,7 +221,28 @@ where
     S: futures_util::Stream<Item = Result<B, E>> + Unpin,
     B: AsRef<[u8]>,
 {
-    rewriter::writer::convert_html_stream_to_markdown(stream, custom, commonmark, url).await
+    rewrite_html_stream_with_options(
+        stream,
+        &ConversionOptions::from_parts(custom, commonmark, url),
+    )
+    .await
+}
+
+/// Convert an async stream of HTML byte chunks into markdown using the conversion options.
+///
+/// # Arguments
+/// * `stream` - an async stream of byte chunks
+/// * `options` - controls the markdown output
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+pub async fn rewrite_html_stream_with_options<S, B, E>(
+    stream: S,
+    options: &ConversionOptions,
+) -> Result<String, StreamConvertError<E>>
+where
+    S: futures_util::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    rewriter::writer::convert_html_stream_to_markdown_with_options(stream, options).await
 }
 
 /// Called after all processing has been finished
@@ -162,7 +256,23 @@ pub fn clean_markdown(input: &str) -> String {
 ///
 /// Clears excessive punctuation that would be trimmed by renderer anyway
 pub fn clean_markdown_bytes(input: &Vec<u8>) -> String {
-    input.sift_bytes_preserve_newlines()
+    input.sift_bytes_preserve_list_indent()
+}
+
+/// Called after the cleanup when a wrap width is set
+///
+/// Hard-wraps the paragraphs and list items at the width, keeping code, tables and links whole
+pub fn wrap_markdown(input: &str, width: usize) -> String {
+    extended::wrap::wrap_markdown(input, width)
+}
+
+/// Wrap the cleaned markdown at the wrap width of the options.
+#[inline]
+pub(crate) fn wrap_markdown_opt(markdown: String, options: &ConversionOptions) -> String {
+    match options.wrap_width {
+        Some(width) if width > 0 => wrap_markdown(&markdown, width),
+        _ => markdown,
+    }
 }
 
 /// Check if a byte needs markdown escaping.
@@ -184,34 +294,58 @@ pub fn contains_markdown_chars(input: &str) -> bool {
     input.as_bytes().iter().any(|&b| is_special_byte(b))
 }
 
+/// A decoded HTML entity.
+#[derive(Debug, Clone, Copy)]
+enum Entity {
+    /// The text of a named entity.
+    Named(&'static str),
+    /// The code point of a numeric entity.
+    Numeric(char),
+}
+
+impl Entity {
+    /// The text of the entity, the code point is encoded into the buffer without allocating.
+    #[inline]
+    fn as_str(self, buffer: &mut [u8; 4]) -> &str {
+        match self {
+            Entity::Named(text) => text,
+            Entity::Numeric(c) => c.encode_utf8(buffer),
+        }
+    }
+}
+
 /// Decode a single HTML entity at the start of a byte slice.
-/// Returns the decoded string and the number of bytes consumed, or None if not a recognized entity.
-/// Handles named entities (&amp; &lt; &gt; &quot; &nbsp; &apos;) and numeric (&#N; &#xH;).
+/// Returns the decoded entity and the number of bytes consumed, or None if not a recognized entity.
+/// Handles the HTML5 named entities (&amp; &copy; &hellip; …) and numeric ones (&#N; &#xH;).
 #[inline]
-fn decode_html_entity(bytes: &[u8]) -> Option<(&'static str, usize)> {
+fn decode_html_entity(bytes: &[u8]) -> Option<(Entity, usize)> {
     debug_assert_eq!(bytes[0], b'&');
 
-    // Find the semicolon (cap search at 10 bytes for perf — longest named entity we care about is &nbsp; = 6)
-    let limit = bytes.len().min(12);
+    // Find the semicolon (cap the search at the longest named entity, numeric ones are shorter)
+    let limit = bytes.len().min(LONGEST_NAMED_ENTITY + 2);
     let semi = bytes[1..limit].iter().position(|&b| b == b';')?;
     let entity = &bytes[1..semi + 1]; // between & and ;
     let consumed = semi + 2; // includes & and ;
 
-    match entity {
-        b"amp" => Some(("&", consumed)),
-        b"lt" => Some(("\\<", consumed)),
-        b"gt" => Some(("\\>", consumed)),
-        b"quot" => Some(("\"", consumed)),
-        b"apos" => Some(("'", consumed)),
-        b"nbsp" => Some(("", consumed)), // strip non-breaking spaces like before
-        _ if entity.first() == Some(&b'#') => decode_numeric_entity(entity, consumed),
-        _ => None,
+    if entity.first() == Some(&b'#') {
+        decode_numeric_entity(entity).map(|c| (Entity::Numeric(c), consumed))
+    } else {
+        named_entity(entity).map(|text| (Entity::Named(text), consumed))
     }
 }
 
+/// The characters of the windows-1252 bytes 0x80 to 0x9F, read by browsers in place of the C1 controls.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
 /// Decode numeric HTML entities: &#39; &#x27; etc.
+/// Out of range code points and surrogates become U+FFFD, as in browsers.
 #[inline]
-fn decode_numeric_entity(entity: &[u8], consumed: usize) -> Option<(&'static str, usize)> {
+fn decode_numeric_entity(entity: &[u8]) -> Option<char> {
     let (digits, radix) = if entity.get(1) == Some(&b'x') || entity.get(1) == Some(&b'X') {
         (&entity[2..], 16)
     } else {
@@ -222,7 +356,7 @@ fn decode_numeric_entity(entity: &[u8], consumed: usize) -> Option<(&'static str
         return None;
     }
 
-    // Parse into a u32 without allocation
+    // Parse into a u32 without allocation, saturating past the last code point
     let mut val: u32 = 0;
     for &b in digits {
         let d = match b {
@@ -231,24 +365,43 @@ fn decode_numeric_entity(entity: &[u8], consumed: usize) -> Option<(&'static str
             b'A'..=b'F' if radix == 16 => (b - b'A' + 10) as u32,
             _ => return None,
         };
-        val = val.checked_mul(radix)?.checked_add(d)?;
+        val = val.saturating_mul(radix).saturating_add(d);
     }
 
-    // Map common code points to static strings to avoid allocation
     match val {
-        0x26 => Some(("&", consumed)),          // &
-        0x3C => Some(("\\<", consumed)),         // <
-        0x3E => Some(("\\>", consumed)),         // >
-        0x22 => Some(("\"", consumed)),          // "
-        0x27 => Some(("'", consumed)),           // '
-        0xA0 => Some(("", consumed)),            // nbsp
-        0x2014 => Some(("\u{2014}", consumed)),  // em dash
-        0x2013 => Some(("\u{2013}", consumed)),  // en dash
-        0x2018 => Some(("\u{2018}", consumed)),  // left single quote
-        0x2019 => Some(("\u{2019}", consumed)),  // right single quote
-        0x201C => Some(("\u{201c}", consumed)),  // left double quote
-        0x201D => Some(("\u{201d}", consumed)),  // right double quote
-        _ => None, // unrecognized numeric entity — pass through as-is
+        0x80..=0x9F => Some(WINDOWS_1252[(val - 0x80) as usize]),
+        0 => Some(char::REPLACEMENT_CHARACTER),
+        _ => Some(char::from_u32(val).unwrap_or(char::REPLACEMENT_CHARACTER)),
+    }
+}
+
+/// The text starts like the rest of a character reference: `lt;` or `#60;`.
+#[inline]
+fn is_reference_rest(bytes: &[u8]) -> bool {
+    let name = bytes.strip_prefix(b"#").unwrap_or(bytes);
+    let len = name
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric())
+        .count();
+
+    len > 0 && name.get(len) == Some(&b';')
+}
+
+/// Write the decoded entity as markdown text: the markdown chars are escaped.
+/// A decoded `&` starting a reference with the text after it (`&amp;lt;`) is escaped too.
+#[inline]
+fn push_entity_markdown(output: &mut String, entity: Entity, rest: &[u8]) {
+    let mut buffer = [0; 4];
+    let decoded = entity.as_str(&mut buffer);
+
+    match decoded.as_bytes() {
+        // strip non-breaking spaces (U+00A0) like before
+        b"\xC2\xA0" => (),
+        &[b] if needs_escape(b) || (b == b'&' && is_reference_rest(rest)) => {
+            output.push('\\');
+            output.push(b as char);
+        }
+        _ => output.push_str(decoded),
     }
 }
 
@@ -282,8 +435,8 @@ pub fn replace_markdown_chars_opt(input: &str) -> Option<String> {
                     i += 1;
                 } else if b == b'&' {
                     // Decode HTML entities in a single pass
-                    if let Some((decoded, len)) = decode_html_entity(&bytes[i..]) {
-                        output.push_str(decoded);
+                    if let Some((entity, len)) = decode_html_entity(&bytes[i..]) {
+                        push_entity_markdown(&mut output, entity, &bytes[i + len..]);
                         i += len;
                     } else {
                         output.push('&');
@@ -305,6 +458,43 @@ pub fn replace_markdown_chars_opt(input: &str) -> Option<String> {
     }
 }
 
+/// Decode the HTML entities of code or plain text without escaping the markdown chars.
+/// Returns None if no changes needed (avoids allocation).
+#[inline]
+pub(crate) fn decode_text_opt(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let first_amp = bytes.iter().position(|&b| b == b'&')?;
+    let mut output = String::with_capacity(input.len());
+
+    output.push_str(&input[..first_amp]);
+
+    let mut i = first_amp;
+    while i < bytes.len() {
+        if bytes[i] == b'&' {
+            if let Some((entity, len)) = decode_html_entity(&bytes[i..]) {
+                let mut buffer = [0; 4];
+                // code is verbatim: no escapes and non-breaking spaces are kept as spaces
+                output.push_str(match entity.as_str(&mut buffer) {
+                    "\u{a0}" => " ",
+                    decoded => decoded,
+                });
+                i += len;
+            } else {
+                output.push('&');
+                i += 1;
+            }
+        } else {
+            let segment_start = i;
+            while i < bytes.len() && bytes[i] != b'&' {
+                i += 1;
+            }
+            output.push_str(&input[segment_start..i]);
+        }
+    }
+
+    Some(output)
+}
+
 /// Replace the markdown chars cleanly.
 /// Optimized to scan bytes and process in bulk segments.
 #[inline]
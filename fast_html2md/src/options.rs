@@ -0,0 +1,439 @@
+#[cfg(feature = "rewriter")]
+use crate::rewriter::custom::{CustomHandler, CustomHandlers};
+use std::collections::HashSet;
+use url::Url;
+
+/// Default chunk size used when feeding html into the streaming rewriter.
+pub const DEFAULT_CHUNK_SIZE: usize = 8192;
+
+/// How the cells covered by a `colspan` or `rowspan` are written in the markdown grid.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpanPolicy {
+    /// Leave the covered cells empty.
+    #[default]
+    Blank,
+    /// Repeat the text of the spanning cell in every covered cell.
+    Repeat,
+}
+
+/// Which candidate of a `srcset` is used as the image url.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSourcePolicy {
+    /// The widest or densest candidate.
+    #[default]
+    Largest,
+    /// The first candidate.
+    First,
+    /// The widest candidate not wider than the given width, the narrowest when none fits.
+    MaxWidth(u32),
+}
+
+/// How links are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// Inline links: `[text](url)`.
+    #[default]
+    Inline,
+    /// Reference links: `[text][1]`, with the definitions appended at the end of the document.
+    /// The definitions are written by the `*_with_options` entry points, not by the bare
+    /// rewriter settings.
+    Referenced,
+}
+
+/// The marker of the unordered list items.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BulletMarker {
+    /// `* item`
+    #[default]
+    Asterisk,
+    /// `- item`
+    Dash,
+    /// `+ item`
+    Plus,
+}
+
+impl BulletMarker {
+    /// The marker character.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BulletMarker::Asterisk => "*",
+            BulletMarker::Dash => "-",
+            BulletMarker::Plus => "+",
+        }
+    }
+}
+
+/// The delimiter of emphasis and strong emphasis.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EmphasisMarker {
+    /// `*em*` and `**strong**`
+    #[default]
+    Asterisk,
+    /// `_em_` and `__strong__`
+    Underscore,
+}
+
+impl EmphasisMarker {
+    /// The single delimiter of emphasis.
+    pub fn single(&self) -> &'static str {
+        match self {
+            EmphasisMarker::Asterisk => "*",
+            EmphasisMarker::Underscore => "_",
+        }
+    }
+
+    /// The double delimiter of strong emphasis.
+    pub fn double(&self) -> &'static str {
+        match self {
+            EmphasisMarker::Asterisk => "**",
+            EmphasisMarker::Underscore => "__",
+        }
+    }
+}
+
+/// How headings are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HeadingStyle {
+    /// `# Title`
+    #[default]
+    Atx,
+    /// `Title` underlined with `===` or `---`, the headings below `<h2>` stay ATX.
+    Setext,
+}
+
+/// The character of the code fences.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FenceMarker {
+    /// ```` ``` ````
+    #[default]
+    Backtick,
+    /// `~~~`
+    Tilde,
+}
+
+impl FenceMarker {
+    /// The fence character.
+    pub fn as_char(&self) -> char {
+        match self {
+            FenceMarker::Backtick => '`',
+            FenceMarker::Tilde => '~',
+        }
+    }
+}
+
+/// The thematic break written for `<hr>`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BreakMarker {
+    /// `---`
+    #[default]
+    Dash,
+    /// `***`
+    Asterisk,
+    /// `___`
+    Underscore,
+}
+
+impl BreakMarker {
+    /// The thematic break.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BreakMarker::Dash => "---",
+            BreakMarker::Asterisk => "***",
+            BreakMarker::Underscore => "___",
+        }
+    }
+}
+
+/// The markers of the markdown output, to match the lint rules of a repository.
+///
+/// ```
+/// # #[cfg(feature = "rewriter")]
+/// # {
+/// use html2md::{BulletMarker, ConversionOptions, EmphasisMarker, MarkdownStyle};
+///
+/// let style = MarkdownStyle::default()
+///     .with_bullet(BulletMarker::Dash)
+///     .with_strong(EmphasisMarker::Underscore);
+/// let options = ConversionOptions::default().with_style(style);
+///
+/// let md = html2md::rewrite_html_with_options("<ul><li><b>A</b></li></ul>", &options);
+/// assert_eq!(md, "- __A__");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownStyle {
+    /// The marker of the unordered list items.
+    pub bullet: BulletMarker,
+    /// The delimiter of emphasis.
+    pub emphasis: EmphasisMarker,
+    /// The delimiter of strong emphasis.
+    pub strong: EmphasisMarker,
+    /// How headings are written.
+    pub heading: HeadingStyle,
+    /// The thematic break written for `<hr>`.
+    pub thematic_break: BreakMarker,
+    /// The character of the code fences.
+    pub code_fence: FenceMarker,
+}
+
+impl Default for MarkdownStyle {
+    fn default() -> Self {
+        Self {
+            bullet: BulletMarker::Asterisk,
+            emphasis: EmphasisMarker::Asterisk,
+            strong: EmphasisMarker::Asterisk,
+            heading: HeadingStyle::Atx,
+            thematic_break: BreakMarker::Dash,
+            code_fence: FenceMarker::Backtick,
+        }
+    }
+}
+
+impl MarkdownStyle {
+    /// Set the marker of the unordered list items.
+    pub fn with_bullet(mut self, bullet: BulletMarker) -> Self {
+        self.bullet = bullet;
+        self
+    }
+
+    /// Set the delimiter of emphasis.
+    pub fn with_emphasis(mut self, emphasis: EmphasisMarker) -> Self {
+        self.emphasis = emphasis;
+        self
+    }
+
+    /// Set the delimiter of strong emphasis.
+    pub fn with_strong(mut self, strong: EmphasisMarker) -> Self {
+        self.strong = strong;
+        self
+    }
+
+    /// Set how headings are written.
+    pub fn with_heading(mut self, heading: HeadingStyle) -> Self {
+        self.heading = heading;
+        self
+    }
+
+    /// Set the thematic break written for `<hr>`.
+    pub fn with_thematic_break(mut self, thematic_break: BreakMarker) -> Self {
+        self.thematic_break = thematic_break;
+        self
+    }
+
+    /// Set the character of the code fences.
+    pub fn with_code_fence(mut self, code_fence: FenceMarker) -> Self {
+        self.code_fence = code_fence;
+        self
+    }
+
+    /// The prefix of a heading: `## ` or the blank line before a setext heading, the
+    /// paragraph before it would be underlined too otherwise.
+    pub(crate) fn heading_prefix(&self, level: usize) -> &'static str {
+        match level {
+            1 | 2 if self.heading == HeadingStyle::Setext => "\n\n",
+            1 => "# ",
+            2 => "## ",
+            3 => "### ",
+            4 => "#### ",
+            5 => "##### ",
+            _ => "###### ",
+        }
+    }
+
+    /// The thematic break of an `<hr>` between blank lines, it would underline the paragraph
+    /// before it otherwise.
+    pub(crate) fn thematic_break_block(&self) -> String {
+        format!("\n\n{}\n\n", self.thematic_break.as_str())
+    }
+
+    /// The underline of a setext heading.
+    pub(crate) fn heading_underline(&self, level: usize) -> Option<&'static str> {
+        match level {
+            1 if self.heading == HeadingStyle::Setext => Some("\n==="),
+            2 if self.heading == HeadingStyle::Setext => Some("\n---"),
+            _ => None,
+        }
+    }
+}
+
+/// Options used by every conversion entry point.
+///
+/// Build it with the `with_*` methods and hand it to the `*_with_options` functions:
+///
+/// ```
+/// # #[cfg(feature = "rewriter")]
+/// # {
+/// use html2md::ConversionOptions;
+///
+/// let options = ConversionOptions::default()
+///     .with_commonmark(true)
+///     .with_url(url::Url::parse("https://example.com").ok());
+///
+/// let md = html2md::rewrite_html_with_options(r#"<a href="/a">A</a>"#, &options);
+/// assert_eq!(md, "[A](https://example.com/a)");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionOptions {
+    /// Adjust the markdown output to commonmark.
+    pub commonmark: bool,
+    /// Selectors of the elements to drop from the output.
+    /// The scraper backend only matches these against tag names.
+    pub ignore: HashSet<String>,
+    /// Selectors of the elements to convert, everything outside them is dropped.
+    /// The scraper backend only matches tag, `#id` and `.class` selectors.
+    /// The invalid selectors are dropped.
+    pub include: HashSet<String>,
+    /// Base url used to make links absolute.
+    pub url: Option<Url>,
+    /// The chunk size used when writing html into the streaming rewriter.
+    pub chunk_size: usize,
+    /// How the cells covered by table spans are written.
+    pub span_policy: SpanPolicy,
+    /// How the image url is picked from `srcset` and `<picture>` sources.
+    pub image_source_policy: ImageSourcePolicy,
+    /// How links are written.
+    pub link_style: LinkStyle,
+    /// Prepend a YAML front matter built from the `<head>` metadata, rewriter only.
+    pub front_matter: bool,
+    /// Convert only the main content of the page, scraper only.
+    pub main_content: bool,
+    /// The markers of the markdown output.
+    pub style: MarkdownStyle,
+    /// The column the paragraphs and list items are hard-wrapped at, no wrapping when `None`.
+    pub wrap_width: Option<usize>,
+    /// The custom element handlers of the rewriter backend.
+    #[cfg(feature = "rewriter")]
+    pub custom_handlers: CustomHandlers,
+}
+
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        Self {
+            commonmark: false,
+            ignore: HashSet::new(),
+            include: HashSet::new(),
+            url: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            span_policy: SpanPolicy::Blank,
+            image_source_policy: ImageSourcePolicy::Largest,
+            link_style: LinkStyle::Inline,
+            front_matter: false,
+            main_content: false,
+            style: MarkdownStyle::default(),
+            wrap_width: None,
+            #[cfg(feature = "rewriter")]
+            custom_handlers: CustomHandlers::default(),
+        }
+    }
+}
+
+impl ConversionOptions {
+    /// Create the default conversion options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adjust the markdown output to commonmark.
+    pub fn with_commonmark(mut self, commonmark: bool) -> Self {
+        self.commonmark = commonmark;
+        self
+    }
+
+    /// Set the selectors of the elements to drop from the output.
+    pub fn with_ignore(mut self, ignore: HashSet<String>) -> Self {
+        self.ignore = ignore;
+        self
+    }
+
+    /// Set the selectors of the elements to convert, an empty set converts the whole document.
+    pub fn with_include(mut self, include: HashSet<String>) -> Self {
+        self.include = include;
+        self
+    }
+
+    /// Set the base url used to make links absolute.
+    pub fn with_url(mut self, url: Option<Url>) -> Self {
+        self.url = url;
+        self
+    }
+
+    /// Set the chunk size used by the streaming rewriter.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Set how the cells covered by table spans are written.
+    pub fn with_span_policy(mut self, span_policy: SpanPolicy) -> Self {
+        self.span_policy = span_policy;
+        self
+    }
+
+    /// Set how the image url is picked from `srcset` and `<picture>` sources.
+    pub fn with_image_source_policy(mut self, image_source_policy: ImageSourcePolicy) -> Self {
+        self.image_source_policy = image_source_policy;
+        self
+    }
+
+    /// Set how links are written.
+    pub fn with_link_style(mut self, link_style: LinkStyle) -> Self {
+        self.link_style = link_style;
+        self
+    }
+
+    /// Prepend a YAML front matter built from the `<head>` metadata, rewriter only.
+    pub fn with_front_matter(mut self, front_matter: bool) -> Self {
+        self.front_matter = front_matter;
+        self
+    }
+
+    /// Convert only the main content of the page, scraper only.
+    ///
+    /// The containers are scored by their text and link density, the navigation,
+    /// sidebars and comment threads around the article are dropped.
+    pub fn with_main_content(mut self, main_content: bool) -> Self {
+        self.main_content = main_content;
+        self
+    }
+
+    /// Set the markers of the markdown output.
+    pub fn with_style(mut self, style: MarkdownStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the column the paragraphs and list items are hard-wrapped at.
+    ///
+    /// Fenced code, tables, headings, inline code and link destinations are never broken.
+    pub fn with_wrap_width(mut self, wrap_width: Option<usize>) -> Self {
+        self.wrap_width = wrap_width;
+        self
+    }
+
+    /// Register a custom handler of the rewriter backend for the elements matching the selector.
+    /// The handler of an invalid selector is never run.
+    #[cfg(feature = "rewriter")]
+    pub fn with_custom_handler(
+        mut self,
+        selector: impl Into<String>,
+        handler: impl CustomHandler + 'static,
+    ) -> Self {
+        self.custom_handlers.push(selector, handler);
+        self
+    }
+
+    /// Build the options from the positional arguments of the legacy entry points.
+    pub(crate) fn from_parts(
+        custom: &Option<HashSet<String>>,
+        commonmark: bool,
+        url: &Option<Url>,
+    ) -> Self {
+        Self {
+            commonmark,
+            ignore: custom.clone().unwrap_or_default(),
+            url: url.clone(),
+            ..Default::default()
+        }
+    }
+}
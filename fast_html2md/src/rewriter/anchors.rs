@@ -1,3 +1,7 @@
+use super::decoded_attribute;
+use super::state::RewriterState;
+use crate::extended::base::link::LinkReferences;
+use crate::extended::base::url::resolve_url;
 use lol_html::html_content::{ContentType::Html, Element};
 use percent_encoding::percent_decode_str;
 use std::borrow::Cow;
@@ -21,36 +25,49 @@ fn build_link_suffix(url: &str, needs_angle_brackets: bool) -> String {
     }
 }
 
+/// Build the markdown reference link suffix: `][1]`.
+#[inline]
+fn build_reference_suffix(number: usize) -> String {
+    let number = number.to_string();
+    let mut s = String::with_capacity(number.len() + 3);
+    s.push_str("][");
+    s.push_str(&number);
+    s.push(']');
+    s
+}
+
+/// Build the suffix of the link, registering the url when links are written as references.
+#[inline]
+fn link_suffix(url: &str, references: Option<&mut LinkReferences>) -> String {
+    match references {
+        Some(references) => build_reference_suffix(references.reference(url)),
+        None => {
+            let needs_brackets = url.bytes().any(|b| b.is_ascii_control() || b == b' ');
+            build_link_suffix(url, needs_brackets)
+        }
+    }
+}
+
+/// The definitions of the reference links, appended at the end of the document.
+#[inline]
+pub(crate) fn link_definitions(state: &RewriterState) -> String {
+    state.references.definitions()
+}
+
 /// Rewrite the anchor.
 pub(crate) fn rewrite_anchor_element(
     el: &mut Element,
     _commonmark: bool,
     url: &Option<Url>,
+    references: Option<&mut LinkReferences>,
 ) -> Result<(), std::io::Error> {
-    if let Some(href) = el.get_attribute("href") {
+    if let Some(href) = decoded_attribute(el, "href") {
         let decoded_url: Cow<'_, str> = percent_decode_str(&href).decode_utf8_lossy();
 
-        let resolved_url = if decoded_url.starts_with('/') {
-            match &url {
-                Some(url) => {
-                    if let Ok(u) = url.join(&decoded_url) {
-                        Cow::Owned(u.to_string())
-                    } else {
-                        decoded_url
-                    }
-                }
-                None => decoded_url,
-            }
-        } else {
-            decoded_url
-        };
-
-        let needs_brackets = resolved_url
-            .bytes()
-            .any(|b| b.is_ascii_control() || b == b' ');
+        let resolved_url = resolve_url(url.as_ref(), decoded_url);
 
         el.before("[", Html);
-        el.after(&build_link_suffix(&resolved_url, needs_brackets), Html);
+        el.after(&link_suffix(&resolved_url, references), Html);
     }
     Ok(())
 }
@@ -60,31 +77,15 @@ pub(crate) fn rewrite_anchor_element_send(
     el: &mut lol_html::send::Element,
     _commonmark: bool,
     url: &Option<Url>,
+    references: Option<&mut LinkReferences>,
 ) -> Result<(), std::io::Error> {
-    if let Some(href) = el.get_attribute("href") {
+    if let Some(href) = decoded_attribute(el, "href") {
         let decoded_url: Cow<'_, str> = percent_decode_str(&href).decode_utf8_lossy();
 
-        let resolved_url = if decoded_url.starts_with('/') {
-            match &url {
-                Some(url) => {
-                    if let Ok(u) = url.join(&decoded_url) {
-                        Cow::Owned(u.to_string())
-                    } else {
-                        decoded_url
-                    }
-                }
-                None => decoded_url,
-            }
-        } else {
-            decoded_url
-        };
-
-        let needs_brackets = resolved_url
-            .bytes()
-            .any(|b| b.is_ascii_control() || b == b' ');
+        let resolved_url = resolve_url(url.as_ref(), decoded_url);
 
         el.before("[", Html);
-        el.after(&build_link_suffix(&resolved_url, needs_brackets), Html);
+        el.after(&link_suffix(&resolved_url, references), Html);
     }
     Ok(())
 }
@@ -0,0 +1,249 @@
+use super::quotes::quote_lines;
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use super::tables::capture_cell_text;
+use crate::extended::base::code::{code_fence, code_language, code_span};
+use lol_html::html_content::{ContentType::Html, Element, EndTag};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An open `<pre>` block. The text is buffered so the fence is written at the end tag,
+/// once the language of a nested `<code>` is known.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CodeBlock {
+    /// The fence info string.
+    language: Option<String>,
+    /// The text of the block.
+    text: String,
+}
+
+impl CodeBlock {
+    /// Write the fenced code block.
+    fn into_markdown(self, marker: char) -> String {
+        let language = self.language.unwrap_or_default();
+        let fence = code_fence(&self.text, marker);
+        let mut out = String::with_capacity(self.text.len() + language.len() + fence.len() * 2 + 4);
+
+        out.push('\n');
+        out.push_str(&fence);
+        out.push_str(&language);
+        out.push('\n');
+        out.push_str(&self.text);
+        if !self.text.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&fence);
+        out.push('\n');
+
+        out
+    }
+}
+
+/// A `<pre>` block is open, its text is buffered.
+#[inline]
+pub(crate) fn in_code_block(state: &RewriterState) -> bool {
+    state.code.is_some()
+}
+
+/// A `<pre>` block or an inline `<code>` is open, its text is buffered.
+#[inline]
+pub(crate) fn in_code(state: &RewriterState) -> bool {
+    state.code.is_some() || state.code_span.is_some()
+}
+
+/// Buffer the text of the open code block or inline code.
+#[inline]
+pub(crate) fn capture_code_text(state: &mut RewriterState, text: &str) {
+    if let Some(block) = state.code.as_mut() {
+        block.text.push_str(text);
+    } else if let Some(span) = state.code_span.as_mut() {
+        span.push_str(text);
+    }
+}
+
+/// Take the code block left open at the end of the document.
+#[inline]
+pub(crate) fn take_code_block(state: &mut RewriterState) -> Option<String> {
+    let marker = state.code_fence.as_char();
+    state.code.take().map(|block| block.into_markdown(marker))
+}
+
+/// The language declared on the element.
+#[inline]
+fn element_language(
+    class: Option<String>,
+    data_lang: Option<String>,
+    data_language: Option<String>,
+) -> Option<String> {
+    code_language(class.as_deref(), data_lang.or(data_language).as_deref())
+}
+
+/// Open a code block, the language of the `<pre>` wins over the enclosing element.
+#[inline]
+fn open_code_block(state: &mut RewriterState, language: Option<String>) {
+    let language = language.or_else(|| state.code_hint.clone());
+
+    state.code = Some(CodeBlock {
+        language,
+        text: String::new(),
+    });
+}
+
+/// Handle an element of the open code block: a nested `<code>` names the language
+/// when the `<pre>` did not, line breaks are kept.
+#[inline]
+fn handle_inner_element(state: &mut RewriterState, tag_name: &str, language: Option<String>) {
+    if let Some(block) = state.code.as_mut() {
+        match tag_name {
+            "code" if block.language.is_none() => block.language = language,
+            "br" => block.text.push('\n'),
+            _ => (),
+        }
+    }
+}
+
+/// Write the inline code at its end tag, the delimiter is chosen from the text.
+/// Inside a quote the code is a quoted line like the text around it.
+#[inline]
+fn close_code_span(state: &mut RewriterState, end: &mut EndTag, quote_depth: usize) {
+    if let Some(text) = state.code_span.take() {
+        let span = code_span(&text);
+
+        // spanning cells repeat their text
+        capture_cell_text(state, &span);
+
+        if quote_depth == 0 {
+            end.before(&span, Html);
+        } else {
+            end.before(&quote_lines(&span, quote_depth), Html);
+            end.before("\n", Html);
+        }
+    }
+}
+
+/// Write the code block at the end of the `<pre>`, every line of it is quoted inside a quote.
+#[inline]
+fn close_code_block(state: &mut RewriterState, end: &mut EndTag, quote_depth: usize) {
+    if let Some(block) = take_code_block(state) {
+        if quote_depth == 0 {
+            end.before(&block, Html);
+        } else {
+            end.before(&quote_lines(&block, quote_depth), Html);
+        }
+    }
+}
+
+/// Function to handle code blocks
+///
+/// The `<pre>` text is written as a fenced block. The language is read from
+/// `data-lang` and `language-*`, `lang-*` or `highlight-source-*` classes on the
+/// `<pre>`, a nested `<code>` or an enclosing element. Inline `<code>` and `<samp>`
+/// are buffered too, the fence and delimiters are longer than any backtick run.
+#[inline]
+pub(crate) fn handle_code_element(
+    element: &mut Element,
+    state: &SharedState,
+    quote_depth: &AtomicUsize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tag_name = element.tag_name();
+    let language = element_language(
+        element.get_attribute("class"),
+        element.get_attribute("data-lang"),
+        element.get_attribute("data-language"),
+    );
+
+    if in_code_block(&state.borrow()) {
+        handle_inner_element(&mut state.borrow_mut(), &tag_name, language);
+    } else if state.borrow().code_span.is_some() {
+        // the markup inside inline code is dropped
+    } else if tag_name == "pre" {
+        if let Some(end_tag_handlers) = element.end_tag_handlers() {
+            let quote_depth = quote_depth.load(Ordering::Relaxed);
+            open_code_block(&mut state.borrow_mut(), language);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |end| {
+                close_code_block(&mut state.borrow_mut(), end, quote_depth);
+                Ok(())
+            }));
+        }
+    } else if tag_name == "code" || tag_name == "samp" {
+        if let Some(end_tag_handlers) = element.end_tag_handlers() {
+            state.borrow_mut().code_span = Some(String::new());
+            let quote_depth = quote_depth.load(Ordering::Relaxed);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |end| {
+                close_code_span(&mut state.borrow_mut(), end, quote_depth);
+                Ok(())
+            }));
+        }
+    } else if language.is_some() {
+        // an enclosing element such as `<div class="highlight highlight-source-rust">`
+        if let Some(end_tag_handlers) = element.end_tag_handlers() {
+            state.borrow_mut().code_hint = language;
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end| {
+                state.borrow_mut().code_hint = None;
+                Ok(())
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// Function to handle code blocks (send)
+#[inline]
+pub(crate) fn handle_code_element_send(
+    element: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+    quote_depth: &AtomicUsize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tag_name = element.tag_name();
+    let language = element_language(
+        element.get_attribute("class"),
+        element.get_attribute("data-lang"),
+        element.get_attribute("data-language"),
+    );
+
+    if in_code_block(&lock_state(state)) {
+        handle_inner_element(&mut lock_state(state), &tag_name, language);
+    } else if lock_state(state).code_span.is_some() {
+        // the markup inside inline code is dropped
+    } else if tag_name == "pre" {
+        if let Some(end_tag_handlers) = element.end_tag_handlers() {
+            let quote_depth = quote_depth.load(Ordering::Relaxed);
+            open_code_block(&mut lock_state(state), language);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+                close_code_block(&mut lock_state(&state), end, quote_depth);
+                Ok(())
+            }));
+        }
+    } else if tag_name == "code" || tag_name == "samp" {
+        if let Some(end_tag_handlers) = element.end_tag_handlers() {
+            lock_state(state).code_span = Some(String::new());
+            let quote_depth = quote_depth.load(Ordering::Relaxed);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+                close_code_span(&mut lock_state(&state), end, quote_depth);
+                Ok(())
+            }));
+        }
+    } else if language.is_some() {
+        // an enclosing element such as `<div class="highlight highlight-source-rust">`
+        if let Some(end_tag_handlers) = element.end_tag_handlers() {
+            lock_state(state).code_hint = language;
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+                lock_state(&state).code_hint = None;
+                Ok(())
+            }));
+        }
+    }
+
+    Ok(())
+}
@@ -1,6 +1,5 @@
-#[allow(dead_code)]
-
 /// Counter utility methods
+#[allow(dead_code)]
 pub trait Counter {
     /// Reset the counter.
     fn reset(&mut self);
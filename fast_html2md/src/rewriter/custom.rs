@@ -0,0 +1,169 @@
+use lol_html::html_content::{ContentType, Element};
+use lol_html::HandlerTypes;
+use std::sync::Arc;
+
+/// The element handed to a [`CustomHandler`].
+///
+/// The markdown written with [`before`](Self::before), [`after`](Self::after) and
+/// [`replace`](Self::replace) is written verbatim into the output, it is neither html nor
+/// markdown escaped.
+/// The attribute values are the raw source values, their character references are not decoded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CustomElement {
+    tag_name: String,
+    attributes: Vec<(String, String)>,
+    before: String,
+    after: String,
+    replacement: Option<String>,
+}
+
+impl CustomElement {
+    /// The lowercase tag name of the element.
+    pub fn tag_name(&self) -> &str {
+        &self.tag_name
+    }
+
+    /// The value of the attribute.
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The attributes of the element in source order.
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    /// Write markdown before the element.
+    pub fn before(&mut self, markdown: &str) {
+        self.before.push_str(markdown);
+    }
+
+    /// Write markdown after the element.
+    pub fn after(&mut self, markdown: &str) {
+        self.after.push_str(markdown);
+    }
+
+    /// Write markdown instead of the element and its content.
+    pub fn replace(&mut self, markdown: &str) {
+        self.replacement = Some(markdown.to_string());
+    }
+}
+
+/// A handler of the rewriter backend for the elements matching a selector.
+///
+/// The built-in conversion still runs for the element unless it is replaced.
+/// Closures taking a `&mut CustomElement` are handlers:
+///
+/// ```
+/// # #[cfg(feature = "rewriter")]
+/// # {
+/// use html2md::{ConversionOptions, CustomElement};
+///
+/// let options = ConversionOptions::default().with_custom_handler(
+///     "x-note",
+///     |el: &mut CustomElement| {
+///         let kind = el.get_attribute("kind").unwrap_or("note").to_uppercase();
+///         el.before(&format!("> [!{kind}]\n> "));
+///     },
+/// );
+///
+/// let md = html2md::rewrite_html_with_options(r#"<x-note kind="tip">Read it</x-note>"#, &options);
+/// assert_eq!(md, "> [!TIP]\n> Read it");
+/// # }
+/// ```
+pub trait CustomHandler: Send + Sync {
+    /// Read the element and write its markdown.
+    fn handle(&self, element: &mut CustomElement);
+}
+
+impl<F> CustomHandler for F
+where
+    F: Fn(&mut CustomElement) + Send + Sync,
+{
+    fn handle(&self, element: &mut CustomElement) {
+        self(element)
+    }
+}
+
+/// The custom handlers of the rewriter backend with their selectors, in registration order.
+#[derive(Clone, Default)]
+pub struct CustomHandlers(Vec<(String, Arc<dyn CustomHandler>)>);
+
+impl CustomHandlers {
+    /// Register the handler for the elements matching the selector.
+    pub fn push(&mut self, selector: impl Into<String>, handler: impl CustomHandler + 'static) {
+        self.0.push((selector.into(), Arc::new(handler)));
+    }
+
+    /// No handler is registered.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The number of registered handlers.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The selectors and their handlers.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &(String, Arc<dyn CustomHandler>)> {
+        self.0.iter()
+    }
+}
+
+impl std::fmt::Debug for CustomHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(selector, _)| selector))
+            .finish()
+    }
+}
+
+impl PartialEq for CustomHandlers {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(other.0.iter())
+                .all(|(a, b)| a.0 == b.0 && std::ptr::addr_eq(Arc::as_ptr(&a.1), Arc::as_ptr(&b.1)))
+    }
+}
+
+impl Eq for CustomHandlers {}
+
+/// Run the custom handler on the element.
+pub(crate) fn handle_custom_element<H: HandlerTypes>(
+    el: &mut Element<'_, '_, H>,
+    handler: &dyn CustomHandler,
+) {
+    let mut element = CustomElement {
+        tag_name: el.tag_name(),
+        attributes: el
+            .attributes()
+            .iter()
+            .map(|attribute| (attribute.name(), attribute.value()))
+            .collect(),
+        ..Default::default()
+    };
+
+    handler.handle(&mut element);
+
+    // the html content type writes the markdown verbatim, the text one would turn `<`, `>`
+    // and `&` into character references
+
+    if let Some(markdown) = element.replacement {
+        el.replace(&markdown, ContentType::Html);
+        return;
+    }
+
+    if !element.before.is_empty() {
+        el.before(&element.before, ContentType::Html);
+    }
+    if !element.after.is_empty() {
+        el.after(&element.after, ContentType::Html);
+    }
+}
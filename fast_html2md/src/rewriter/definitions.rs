@@ -0,0 +1,108 @@
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use crate::extended::base::definition::definition_prefix;
+use lol_html::html_content::{ContentType::Text, Element, EndTag};
+
+/// The open strong term of a definition list in strict CommonMark.
+///
+/// The whitespace inside the delimiters would keep them from being read as strong,
+/// the leading whitespace is dropped and the trailing whitespace is held back until more of the term follows.
+#[derive(Debug, Default)]
+pub(crate) struct Term {
+    /// The text of the term started.
+    started: bool,
+    /// The whitespace held back at the end of the text.
+    space: String,
+}
+
+/// Rewrite the elements of a definition list: `Term` and `: Definition` lines.
+/// The definition starts like a list item, a `<p>` inside it stays on the line of the prefix.
+/// In strict CommonMark, where there is no definition syntax, the term is strong.
+pub(crate) fn rewrite_definition_element<H: lol_html::HandlerTypes>(
+    el: &mut Element<'_, '_, H>,
+    commonmark: bool,
+    strong: &str,
+    list_item_start: &mut bool,
+) {
+    match el.tag_name().as_str() {
+        "dt" => {
+            el.before("\n", Text);
+            if commonmark {
+                el.prepend(strong, Text);
+                el.append(strong, Text);
+            }
+            el.after("\n", Text);
+        }
+        "dd" => {
+            el.before("\n", Text);
+            el.prepend(definition_prefix(commonmark), Text);
+            el.after("\n", Text);
+            *list_item_start = true;
+        }
+        _ => {
+            el.before("\n", Text);
+            el.after("\n", Text);
+        }
+    }
+}
+
+/// Trim the outer whitespace of the text of the open strong term.
+#[inline]
+pub(crate) fn trim_term_text(state: &mut RewriterState, text: &str) -> Option<String> {
+    let term = state.term.as_mut()?;
+    let text = if term.started {
+        text
+    } else {
+        text.trim_start_matches(|c: char| c.is_ascii_whitespace())
+    };
+    let content = text.trim_end_matches(|c: char| c.is_ascii_whitespace());
+
+    if content.is_empty() {
+        term.space.push_str(text);
+        return Some(String::new());
+    }
+
+    term.started = true;
+
+    let mut out = std::mem::take(&mut term.space);
+    out.push_str(content);
+    term.space.push_str(&text[content.len()..]);
+
+    Some(out)
+}
+
+/// Write the whitespace held back by the open strong term before the element inside it.
+#[inline]
+pub(crate) fn write_term_space<H: lol_html::HandlerTypes>(
+    state: &mut RewriterState,
+    el: &mut Element<'_, '_, H>,
+) {
+    if let Some(term) = state.term.as_mut().filter(|term| !term.space.is_empty()) {
+        el.before(&std::mem::take(&mut term.space), Text);
+    }
+}
+
+/// Open the strong term, its held back whitespace is dropped at the end tag.
+pub(crate) fn open_term(el: &mut Element, state: &SharedState) {
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        state.borrow_mut().term = Some(Term::default());
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |_end| {
+            state.borrow_mut().term = None;
+            Ok(())
+        }));
+    }
+}
+
+/// Open the strong term, its held back whitespace is dropped at the end tag (send).
+pub(crate) fn open_term_send(el: &mut lol_html::send::Element, state: &SharedStateSend) {
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        lock_state(state).term = Some(Term::default());
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+            lock_state(&state).term = None;
+            Ok(())
+        }));
+    }
+}
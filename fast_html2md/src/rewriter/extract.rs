@@ -0,0 +1,258 @@
+use super::decoded_attribute;
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use crate::extended::base::image::ImageSources;
+use crate::extended::base::url::resolve_url;
+use crate::ImageSourcePolicy;
+use lol_html::html_content::{Element, EndTag};
+use percent_encoding::percent_decode_str;
+use std::borrow::Cow;
+use url::Url;
+
+/// An outgoing link of the document.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExtractedLink {
+    /// The resolved url.
+    pub url: String,
+    /// The text of the anchor.
+    pub text: String,
+    /// The `title` attribute.
+    pub title: Option<String>,
+    /// The `rel` attribute values, such as `nofollow`.
+    pub rel: Vec<String>,
+    /// The url is on the host of the page url of the options, or stays relative.
+    pub internal: bool,
+}
+
+/// An image of the document.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExtractedImage {
+    /// The resolved url.
+    pub url: String,
+    /// The `alt` attribute.
+    pub alt: String,
+    /// The `title` attribute.
+    pub title: Option<String>,
+    /// The url is on the host of the page url of the options, or stays relative.
+    pub internal: bool,
+}
+
+/// The markdown with the links and images collected in the same pass.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConversionOutput {
+    /// The markdown document.
+    pub markdown: String,
+    /// The outgoing links in document order.
+    pub links: Vec<ExtractedLink>,
+    /// The images in document order.
+    pub images: Vec<ExtractedImage>,
+}
+
+/// The links and images collected while rewriting.
+#[derive(Debug, Default)]
+pub(crate) struct Extraction {
+    /// The closed links.
+    links: Vec<ExtractedLink>,
+    /// The images.
+    images: Vec<ExtractedImage>,
+    /// The open anchor, its text is captured until the end tag.
+    open_link: Option<ExtractedLink>,
+}
+
+impl Extraction {
+    /// Finish the output with the collected links and images.
+    pub(crate) fn into_output(mut self, markdown: String) -> ConversionOutput {
+        self.close_link();
+
+        ConversionOutput {
+            markdown,
+            links: self.links,
+            images: self.images,
+        }
+    }
+
+    /// Close the open anchor.
+    fn close_link(&mut self) {
+        if let Some(mut link) = self.open_link.take() {
+            link.text = link.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            self.links.push(link);
+        }
+    }
+}
+
+/// The url is on the host of the page url, relative urls are internal.
+/// A `<base href>` on another host moves the links, not the page.
+#[inline]
+fn is_internal(page: Option<&Url>, url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => page.is_some_and(|page| page.host_str() == url.host_str()),
+        Err(_) => true,
+    }
+}
+
+/// Links and images are collected.
+#[inline]
+pub(crate) fn is_extracting(state: &RewriterState) -> bool {
+    state.extraction.is_some()
+}
+
+/// Capture the text of the open anchor.
+#[inline]
+pub(crate) fn capture_link_text(state: &mut RewriterState, text: &str) {
+    if let Some(link) = state
+        .extraction
+        .as_mut()
+        .and_then(|extraction| extraction.open_link.as_mut())
+    {
+        link.text.push_str(text);
+    }
+}
+
+/// Open the link of the anchor.
+#[inline]
+fn open_link(
+    state: &mut RewriterState,
+    url: &Option<Url>,
+    href: String,
+    title: Option<String>,
+    rel: Option<String>,
+) {
+    let base = state.base_url(url).clone();
+
+    if let Some(extraction) = state.extraction.as_mut() {
+        let page = url;
+        let url = resolve_url(base.as_ref(), percent_decode_str(&href).decode_utf8_lossy());
+
+        extraction.close_link();
+        extraction.open_link = Some(ExtractedLink {
+            internal: is_internal(page.as_ref(), &url),
+            url: url.into_owned(),
+            text: String::new(),
+            title,
+            rel: rel
+                .map(|rel| rel.split_ascii_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+        });
+    }
+}
+
+/// Close the link of the anchor.
+#[inline]
+fn close_link(state: &mut RewriterState) {
+    if let Some(extraction) = state.extraction.as_mut() {
+        extraction.close_link();
+    }
+}
+
+/// Collect the image with the url picked for the markdown.
+#[inline]
+fn push_image(
+    state: &mut RewriterState,
+    url: &Option<Url>,
+    policy: ImageSourcePolicy,
+    sources: ImageSources,
+    alt: Option<String>,
+    title: Option<String>,
+) {
+    let base = state.base_url(url).clone();
+    let picture = state.pictures.last().cloned().flatten();
+
+    if let Some(extraction) = state.extraction.as_mut() {
+        let sources = ImageSources { picture, ..sources };
+
+        if let Some(src) = sources.select(policy) {
+            let page = url;
+            let url = resolve_url(base.as_ref(), Cow::Borrowed(src));
+
+            extraction.images.push(ExtractedImage {
+                internal: is_internal(page.as_ref(), &url),
+                url: url.into_owned(),
+                alt: alt.unwrap_or_default(),
+                title,
+            });
+        }
+    }
+}
+
+/// Collect the link or image of the element.
+pub(crate) fn extract_element(
+    el: &mut Element,
+    state: &SharedState,
+    url: &Option<Url>,
+    policy: ImageSourcePolicy,
+) {
+    match el.tag_name().as_str() {
+        "a" => {
+            if let Some(href) = decoded_attribute(el, "href") {
+                open_link(
+                    &mut state.borrow_mut(),
+                    url,
+                    href,
+                    decoded_attribute(el, "title"),
+                    decoded_attribute(el, "rel"),
+                );
+
+                if let Some(end_tag_handlers) = el.end_tag_handlers() {
+                    let state = state.clone();
+                    end_tag_handlers.push(Box::new(move |_end| {
+                        close_link(&mut state.borrow_mut());
+                        Ok(())
+                    }));
+                }
+            }
+        }
+        "img" => {
+            let sources = ImageSources::from_attributes(None, |name| decoded_attribute(el, name));
+            push_image(
+                &mut state.borrow_mut(),
+                url,
+                policy,
+                sources,
+                decoded_attribute(el, "alt"),
+                decoded_attribute(el, "title"),
+            );
+        }
+        _ => (),
+    }
+}
+
+/// Collect the link or image of the element (send).
+pub(crate) fn extract_element_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+    url: &Option<Url>,
+    policy: ImageSourcePolicy,
+) {
+    match el.tag_name().as_str() {
+        "a" => {
+            if let Some(href) = decoded_attribute(el, "href") {
+                open_link(
+                    &mut lock_state(state),
+                    url,
+                    href,
+                    decoded_attribute(el, "title"),
+                    decoded_attribute(el, "rel"),
+                );
+
+                if let Some(end_tag_handlers) = el.end_tag_handlers() {
+                    let state = state.clone();
+                    end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+                        close_link(&mut lock_state(&state));
+                        Ok(())
+                    }));
+                }
+            }
+        }
+        "img" => {
+            let sources = ImageSources::from_attributes(None, |name| decoded_attribute(el, name));
+            push_image(
+                &mut lock_state(state),
+                url,
+                policy,
+                sources,
+                decoded_attribute(el, "alt"),
+                decoded_attribute(el, "title"),
+            );
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,307 @@
+use super::lists::next_item_marker;
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use crate::extended::base::footnote::{is_backlink_class, is_reference_class, note_id, Footnotes};
+use lol_html::html_content::{ContentType, Element, EndTag};
+
+/// The footnotes of the document and the citation or note being written.
+#[derive(Debug, Default)]
+pub(crate) struct FootnoteState {
+    /// The cited notes.
+    footnotes: Footnotes,
+    /// The depth of the open `<sup>` elements.
+    sup_depth: usize,
+    /// The id of the outermost open `<sup>`, the target of the backlinks.
+    sup_id: Option<String>,
+    /// The outermost open `<sup>` is a `<sup class="reference">`.
+    sup_reference: bool,
+    /// The note id and text of the citation link being read.
+    citation: Option<(String, String)>,
+    /// A footnote definition is open.
+    in_note: bool,
+}
+
+/// Open a `<sup>` that may hold a citation.
+#[inline]
+fn open_sup(state: &mut RewriterState, id: Option<String>, reference: bool) {
+    let footnotes = &mut state.footnotes;
+
+    footnotes.sup_depth += 1;
+    if footnotes.sup_depth == 1 {
+        footnotes.sup_id = id;
+        footnotes.sup_reference = reference;
+    }
+}
+
+/// Close the `<sup>`.
+#[inline]
+fn close_sup(state: &mut RewriterState) {
+    let footnotes = &mut state.footnotes;
+
+    footnotes.sup_depth = footnotes.sup_depth.saturating_sub(1);
+    if footnotes.sup_depth == 0 {
+        footnotes.sup_id = None;
+        footnotes.sup_reference = false;
+    }
+}
+
+/// The in-page link inside a `<sup class="reference">` starts a citation.
+#[inline]
+fn start_citation(state: &mut RewriterState, href: Option<String>) -> bool {
+    let footnotes = &mut state.footnotes;
+
+    if !footnotes.sup_reference || footnotes.in_note || footnotes.citation.is_some() {
+        return false;
+    }
+
+    match href.as_deref().and_then(note_id) {
+        Some(id) => {
+            footnotes.citation = Some((id.to_string(), String::new()));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Finish the citation and get its marker: `[^3]`.
+#[inline]
+fn finish_citation(state: &mut RewriterState) -> Option<String> {
+    let footnotes = &mut state.footnotes;
+    let (id, text) = footnotes.citation.take()?;
+    let label = footnotes
+        .footnotes
+        .cite(&id, footnotes.sup_id.clone(), &text);
+
+    Some(format!("[^{label}]"))
+}
+
+/// The definitions of the cited notes missing from the document, appended at the end.
+#[inline]
+pub(crate) fn missing_note_definitions(state: &RewriterState) -> String {
+    state.footnotes.footnotes.missing_definitions()
+}
+
+/// Capture the text of the citation, it only gives the label of the footnote.
+#[inline]
+pub(crate) fn capture_citation_text(state: &mut RewriterState, text: &str) -> bool {
+    match state.footnotes.citation.as_mut() {
+        Some((_, citation)) => {
+            citation.push_str(text);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The prefix of the note when the element is a cited note: `[^3]: `.
+#[inline]
+fn note_prefix(state: &mut RewriterState, id: Option<String>) -> Option<String> {
+    let label = state.footnotes.footnotes.define(id.as_deref()?)?;
+
+    Some(format!("\n[^{label}]: "))
+}
+
+/// The element is a backlink of the open note, dropped with its content.
+#[inline]
+fn is_backlink(
+    state: &RewriterState,
+    tag_name: &str,
+    class: Option<String>,
+    href: Option<String>,
+) -> bool {
+    state.footnotes.in_note
+        && match tag_name {
+            "a" => href.is_some_and(|href| state.footnotes.footnotes.is_backlink(&href)),
+            _ => class.is_some_and(|class| is_backlink_class(&class)),
+        }
+}
+
+/// Track the `<sup>` of the citations, the tags of `<sup class="reference">` are dropped.
+pub(crate) fn handle_footnote_sup(el: &mut Element, state: &SharedState) {
+    let reference = el
+        .get_attribute("class")
+        .is_some_and(|class| is_reference_class(&class));
+    let id = el.get_attribute("id");
+
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        open_sup(&mut state.borrow_mut(), id, reference);
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |_end| {
+            close_sup(&mut state.borrow_mut());
+            Ok(())
+        }));
+    }
+
+    if reference {
+        el.remove_and_keep_content();
+    }
+}
+
+/// Track the `<sup>` of the citations, the tags of `<sup class="reference">` are dropped (send).
+pub(crate) fn handle_footnote_sup_send(el: &mut lol_html::send::Element, state: &SharedStateSend) {
+    let reference = el
+        .get_attribute("class")
+        .is_some_and(|class| is_reference_class(&class));
+    let id = el.get_attribute("id");
+
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        open_sup(&mut lock_state(state), id, reference);
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+            close_sup(&mut lock_state(&state));
+            Ok(())
+        }));
+    }
+
+    if reference {
+        el.remove_and_keep_content();
+    }
+}
+
+/// Rewrite an in-page link inside a `<sup class="reference">` into a footnote reference: `[^3]`.
+pub(crate) fn rewrite_citation(el: &mut Element, state: &SharedState) -> bool {
+    let href = el.get_attribute("href");
+    let Some(end_tag_handlers) = el.end_tag_handlers() else {
+        return false;
+    };
+
+    if !start_citation(&mut state.borrow_mut(), href) {
+        return false;
+    }
+
+    let state = state.clone();
+    end_tag_handlers.push(Box::new(move |end| {
+        if let Some(marker) = finish_citation(&mut state.borrow_mut()) {
+            end.after(&marker, ContentType::Html);
+        }
+        Ok(())
+    }));
+
+    true
+}
+
+/// Rewrite an in-page link inside a `<sup class="reference">` into a footnote reference: `[^3]` (send).
+pub(crate) fn rewrite_citation_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+) -> bool {
+    let href = el.get_attribute("href");
+    let Some(end_tag_handlers) = el.end_tag_handlers() else {
+        return false;
+    };
+
+    if !start_citation(&mut lock_state(state), href) {
+        return false;
+    }
+
+    let state = state.clone();
+    end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+        if let Some(marker) = finish_citation(&mut lock_state(&state)) {
+            end.after(&marker, ContentType::Html);
+        }
+        Ok(())
+    }));
+
+    true
+}
+
+/// Rewrite a cited list item into a footnote definition: `[^3]: Text`.
+pub(crate) fn rewrite_note(
+    el: &mut Element,
+    state: &SharedState,
+    list_item_start: &mut bool,
+) -> bool {
+    let Some(prefix) = note_prefix(&mut state.borrow_mut(), el.get_attribute("id")) else {
+        return false;
+    };
+
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        let mut state_ref = state.borrow_mut();
+        // the note is still an item of its list
+        next_item_marker(&mut state_ref.lists, Default::default());
+        state_ref.footnotes.in_note = true;
+        drop(state_ref);
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |_end| {
+            state.borrow_mut().footnotes.in_note = false;
+            Ok(())
+        }));
+    }
+
+    el.before(&prefix, ContentType::Text);
+    // the text after the note is not part of it
+    el.after("\n\n", ContentType::Text);
+    *list_item_start = true;
+
+    true
+}
+
+/// Rewrite a cited list item into a footnote definition: `[^3]: Text` (send).
+pub(crate) fn rewrite_note_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+    list_item_start: &mut bool,
+) -> bool {
+    let Some(prefix) = note_prefix(&mut lock_state(state), el.get_attribute("id")) else {
+        return false;
+    };
+
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        let mut state_ref = lock_state(state);
+        next_item_marker(&mut state_ref.lists, Default::default());
+        state_ref.footnotes.in_note = true;
+        drop(state_ref);
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+            lock_state(&state).footnotes.in_note = false;
+            Ok(())
+        }));
+    }
+
+    el.before(&prefix, ContentType::Text);
+    // the text after the note is not part of it
+    el.after("\n\n", ContentType::Text);
+    *list_item_start = true;
+
+    true
+}
+
+/// Drop the backlinks of the open note to its citations.
+pub(crate) fn remove_backlink(el: &mut Element, state: &SharedState) -> bool {
+    let tag_name = el.tag_name();
+    let backlink = is_backlink(
+        &state.borrow(),
+        &tag_name,
+        el.get_attribute("class"),
+        el.get_attribute("href"),
+    );
+
+    if backlink {
+        el.remove();
+    }
+
+    backlink
+}
+
+/// Drop the backlinks of the open note to its citations (send).
+pub(crate) fn remove_backlink_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+) -> bool {
+    let tag_name = el.tag_name();
+    let backlink = is_backlink(
+        &lock_state(state),
+        &tag_name,
+        el.get_attribute("class"),
+        el.get_attribute("href"),
+    );
+
+    if backlink {
+        el.remove();
+    }
+
+    backlink
+}
@@ -1,23 +1,36 @@
 use super::anchors::{rewrite_anchor_element, rewrite_anchor_element_send};
+use super::codes::{handle_code_element, handle_code_element_send, in_code};
+use super::definitions::{open_term, open_term_send, rewrite_definition_element};
+use super::extract::{extract_element, extract_element_send, is_extracting};
+use super::footnotes::{
+    handle_footnote_sup, handle_footnote_sup_send, remove_backlink, remove_backlink_send,
+    rewrite_citation, rewrite_citation_send, rewrite_note, rewrite_note_send,
+};
+use super::headings::{open_setext_heading, open_setext_heading_send};
 use super::iframes::{handle_iframe, handle_iframe_send};
-use super::images::{rewrite_image_element, rewrite_image_element_send};
-use super::lists::{handle_list_or_item, handle_list_or_item_send};
+use super::images::{
+    handle_picture_element, handle_picture_element_send, picture_source, rewrite_image_element,
+    rewrite_image_element_send,
+};
+use super::lists::{
+    handle_list_or_item, handle_list_or_item_send, rewrite_checkbox, rewrite_checkbox_send,
+};
+use super::math::{handle_math_element, handle_math_element_send};
+use super::metadata::{
+    handle_metadata_element, handle_metadata_element_send, is_capturing_metadata,
+};
 use super::quotes::{rewrite_blockquote_element, rewrite_blockquote_element_send};
+use super::state::{lock_state, SharedState, SharedStateSend};
 use super::styles::{rewrite_style_element, rewrite_style_element_send};
+use super::tables::{handle_table_element, handle_table_element_send};
 use super::{
     insert_newline_after, insert_newline_after_send, insert_newline_before,
     insert_newline_before_send,
 };
-use lol_html::html_content::{
-    ContentType::{Html, Text},
-    Element,
-};
+use crate::ConversionOptions;
+use lol_html::html_content::{ContentType::Text, Element};
 use std::rc::Rc;
-use std::sync::{
-    atomic::{AtomicUsize},
-    Arc,
-};
-use url::Url;
+use std::sync::{atomic::AtomicUsize, Arc};
 
 /// Handle the lol_html tag (sync).
 ///
@@ -25,23 +38,29 @@ use url::Url;
 /// - `in_table` tracks whether we're inside a <table>.
 /// - `table_row_start` tracks start-of-row so we can emit a leading '|' once per row.
 /// - `list_item_start` is set by list.rs when "* " / "N. " is emitted; used to avoid "*\nText".
+/// - `state` holds the open lists, shared with the end tag handlers.
 #[inline]
 pub fn handle_tag(
     element: &mut Element,
-    commonmark: bool,
-    url: &Option<Url>,
-    list_type: &mut Option<&'static str>,
-    order_counter: &mut usize,
+    options: &ConversionOptions,
+    state: &SharedState,
     quote_depth: Rc<AtomicUsize>,
     in_table: &mut bool,
     table_row_start: &mut bool,
     list_item_start: &mut bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let commonmark = options.commonmark;
+    let url = &options.url;
     let element_name = element.tag_name();
     let element_name = element_name.as_str();
 
     let remove_attrs = commonmark && (element_name == "sub" || element_name == "sup");
 
+    // FOOTNOTES: the class and id of a <sup> are read before its attributes are removed.
+    if element_name == "sup" {
+        handle_footnote_sup(element, state);
+    }
+
     // check common mark includes.
     if remove_attrs {
         let attrs = element
@@ -57,31 +76,26 @@ pub fn handle_tag(
         element.remove_and_keep_content();
     }
 
+    // MATH: math.rs reads the <math> elements and the MathJax scripts, written at their end tag.
+    if handle_math_element(element, state) {
+        return Ok(());
+    }
+
+    // CODE BLOCKS: codes.rs buffers the text of the <pre> and inline <code>, the elements inside only add line breaks.
+    if in_code(&state.borrow()) {
+        return handle_code_element(element, state, &quote_depth);
+    }
+
     // Add the markdown equivalents before/after the element.
     match element_name {
-        "h1" => {
-            element.before("# ", Html);
-            insert_newline_after(element);
-        }
-        "h2" => {
-            element.before("## ", Text);
-            insert_newline_after(element);
-        }
-        "h3" => {
-            element.before("### ", Text);
-            insert_newline_after(element);
-        }
-        "h4" => {
-            element.before("#### ", Text);
-            insert_newline_after(element);
-        }
-        "h5" => {
-            element.before("##### ", Text);
-            insert_newline_after(element);
-        }
-        "h6" => {
-            element.before("###### ", Text);
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(element_name.as_bytes()[1] - b'0');
+            element.before(options.style.heading_prefix(level), Text);
             insert_newline_after(element);
+            // the setext underline lands before the end tag, under the text of the heading
+            if let Some(underline) = options.style.heading_underline(level) {
+                open_setext_heading(element, state, underline);
+            }
         }
 
         // KEY FIX FOR LISTS:
@@ -93,55 +107,77 @@ pub fn handle_tag(
             insert_newline_after(element);
         }
 
+        // <hr> is void, the break is written after it
         "hr" => {
-            insert_newline_before(element);
-            element.append("---", Text);
-            insert_newline_after(element);
+            element.after(&options.style.thematic_break_block(), Text);
         }
         "br" => insert_newline_after(element),
 
-        "a" => {
-            let _ = rewrite_anchor_element(element, commonmark, url);
+        // LINKS: a <base href> in the head is seen before the links of the body.
+        "base" => {
+            let href = element.get_attribute("href");
+            state.borrow_mut().set_base_url(url, href);
         }
-        "img" => {
-            let _ = rewrite_image_element(element, commonmark, url);
-        }
-
-        // TABLES (minimal pipe rendering support)
-        "table" => {
-            *in_table = true;
-            *table_row_start = false;
-        }
-        "tr" => {
-            if *in_table {
-                *table_row_start = true;
+        "a" => {
+            // FOOTNOTES: citations become [^3], the backlinks of the notes are dropped.
+            if remove_backlink(element, state) || rewrite_citation(element, state) {
+                return Ok(());
             }
-            insert_newline_after(element);
+            if is_extracting(&state.borrow()) {
+                extract_element(element, state, url, options.image_source_policy);
+            }
+            let mut state = state.borrow_mut();
+            let (url, references) = state.link_targets(url, options.link_style);
+            let _ = rewrite_anchor_element(element, commonmark, url, references);
         }
-        "th" | "td" => {
-            if *in_table && *table_row_start {
-                element.before("|", Html);
-                *table_row_start = false;
+        "img" => {
+            if is_extracting(&state.borrow()) {
+                extract_element(element, state, url, options.image_source_policy);
             }
+            let state = state.borrow();
+            let _ = rewrite_image_element(
+                element,
+                commonmark,
+                state.base_url(url),
+                options.image_source_policy,
+                picture_source(&state),
+            );
+        }
+        "picture" | "source" => handle_picture_element(element, state),
 
-            if element_name == "th" && commonmark {
-                element.before("** ", Html);
-                element.after("**|", Html);
-            } else {
-                element.after("|", Html);
-            }
+        // TABLES: tables.rs writes the header delimiter row.
+        "table" | "colgroup" | "col" | "tr" | "th" | "td" => {
+            let _ = handle_table_element(element, state, options, in_table, table_row_start);
         }
 
         "iframe" => {
-            let _ = handle_iframe(element);
+            let state = state.borrow();
+            let _ = handle_iframe(element, state.base_url(url));
         }
         "b" | "i" | "s" | "strong" | "em" | "del" => {
-            let _ = rewrite_style_element(element);
+            let _ = rewrite_style_element(element, &options.style);
         }
 
         // LISTS: list.rs sets list_item_start=true when it emits a marker.
+        // a cited <li> is the definition of its footnote
+        "li" if rewrite_note(element, state, list_item_start) => (),
         "ol" | "ul" | "menu" | "li" => {
-            let _ = handle_list_or_item(element, list_type, order_counter, list_item_start);
+            let _ = handle_list_or_item(element, state, options.style.bullet, list_item_start);
+        }
+        "span" => {
+            remove_backlink(element, state);
+        }
+        "input" => rewrite_checkbox(element, list_item_start),
+        "dl" | "dt" | "dd" => {
+            if commonmark && element_name == "dt" {
+                open_term(element, state);
+            }
+            rewrite_definition_element(
+                element,
+                commonmark,
+                options.style.strong.double(),
+                list_item_start,
+            );
         }
 
         "q" | "cite" | "blockquote" => {
@@ -149,16 +185,25 @@ pub fn handle_tag(
         }
 
         "div" | "section" | "header" | "footer" => {
+            // <div class="highlight highlight-source-rust"> names the language of its <pre>
+            if element_name == "div" {
+                let _ = handle_code_element(element, state, &quote_depth);
+            }
             insert_newline_before(element);
             insert_newline_after(element);
         }
         "pre" => {
-            element.before("\n```\n", Html);
-            element.after("\n```\n", Html);
+            let _ = handle_code_element(element, state, &quote_depth);
         }
         "code" | "samp" => {
-            element.before("`", Html);
-            element.after("`", Html);
+            let _ = handle_code_element(element, state, &quote_depth);
+        }
+
+        // FRONT MATTER: metadata.rs reads the <head> metadata.
+        "html" | "body" | "title" | "svg" | "meta" | "link"
+            if is_capturing_metadata(&state.borrow()) =>
+        {
+            handle_metadata_element(element, state, url);
         }
         _ => (),
     }
@@ -170,20 +215,24 @@ pub fn handle_tag(
 #[inline]
 pub fn handle_tag_send(
     element: &mut lol_html::send::Element,
-    commonmark: bool,
-    url: &Option<Url>,
-    list_type: &mut Option<&'static str>,
-    order_counter: &mut usize,
+    options: &ConversionOptions,
+    state: &SharedStateSend,
     quote_depth: Arc<AtomicUsize>,
     in_table: &mut bool,
     table_row_start: &mut bool,
     list_item_start: &mut bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let commonmark = options.commonmark;
+    let url = &options.url;
     let element_name = element.tag_name();
     let element_name = element_name.as_str();
 
     let remove_attrs = commonmark && (element_name == "sub" || element_name == "sup");
 
+    if element_name == "sup" {
+        handle_footnote_sup_send(element, state);
+    }
+
     // check common mark includes.
     if remove_attrs {
         let attrs = element
@@ -199,30 +248,25 @@ pub fn handle_tag_send(
         element.remove_and_keep_content();
     }
 
+    // MATH
+    if handle_math_element_send(element, state) {
+        return Ok(());
+    }
+
+    // CODE BLOCKS
+    if in_code(&lock_state(state)) {
+        return handle_code_element_send(element, state, &quote_depth);
+    }
+
     match element_name {
-        "h1" => {
-            element.before("# ", Html);
-            insert_newline_after_send(element);
-        }
-        "h2" => {
-            element.before("## ", Text);
-            insert_newline_after_send(element);
-        }
-        "h3" => {
-            element.before("### ", Text);
-            insert_newline_after_send(element);
-        }
-        "h4" => {
-            element.before("#### ", Text);
-            insert_newline_after_send(element);
-        }
-        "h5" => {
-            element.before("##### ", Text);
-            insert_newline_after_send(element);
-        }
-        "h6" => {
-            element.before("###### ", Text);
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(element_name.as_bytes()[1] - b'0');
+            element.before(options.style.heading_prefix(level), Text);
             insert_newline_after_send(element);
+            // the setext underline lands before the end tag, under the text of the heading
+            if let Some(underline) = options.style.heading_underline(level) {
+                open_setext_heading_send(element, state, underline);
+            }
         }
 
         // KEY FIX FOR LISTS
@@ -234,54 +278,72 @@ pub fn handle_tag_send(
         }
 
         "hr" => {
-            insert_newline_before_send(element);
-            element.append("---", Text);
-            insert_newline_after_send(element);
+            element.after(&options.style.thematic_break_block(), Text);
         }
         "br" => insert_newline_after_send(element),
 
-        "a" => {
-            let _ = rewrite_anchor_element_send(element, commonmark, url);
+        "base" => {
+            let href = element.get_attribute("href");
+            lock_state(state).set_base_url(url, href);
         }
-        "img" => {
-            let _ = rewrite_image_element_send(element, commonmark, url);
-        }
-
-        // TABLES
-        "table" => {
-            *in_table = true;
-            *table_row_start = false;
-        }
-        "tr" => {
-            if *in_table {
-                *table_row_start = true;
+        "a" => {
+            if remove_backlink_send(element, state) || rewrite_citation_send(element, state) {
+                return Ok(());
             }
-            insert_newline_after_send(element);
+            if is_extracting(&lock_state(state)) {
+                extract_element_send(element, state, url, options.image_source_policy);
+            }
+            let mut state = lock_state(state);
+            let (url, references) = state.link_targets(url, options.link_style);
+            let _ = rewrite_anchor_element_send(element, commonmark, url, references);
         }
-        "th" | "td" => {
-            if *in_table && *table_row_start {
-                element.before("|", Html);
-                *table_row_start = false;
+        "img" => {
+            if is_extracting(&lock_state(state)) {
+                extract_element_send(element, state, url, options.image_source_policy);
             }
+            let state = lock_state(state);
+            let _ = rewrite_image_element_send(
+                element,
+                commonmark,
+                state.base_url(url),
+                options.image_source_policy,
+                picture_source(&state),
+            );
+        }
+        "picture" | "source" => handle_picture_element_send(element, state),
 
-            if element_name == "th" && commonmark {
-                element.before("** ", Html);
-                element.after("**|", Html);
-            } else {
-                element.after("|", Html);
-            }
+        // TABLES: tables.rs writes the header delimiter row.
+        "table" | "colgroup" | "col" | "tr" | "th" | "td" => {
+            let _ = handle_table_element_send(element, state, options, in_table, table_row_start);
         }
 
         "iframe" => {
-            let _ = handle_iframe_send(element);
+            let state = lock_state(state);
+            let _ = handle_iframe_send(element, state.base_url(url));
         }
         "b" | "i" | "s" | "strong" | "em" | "del" => {
-            let _ = rewrite_style_element_send(element);
+            let _ = rewrite_style_element_send(element, &options.style);
         }
 
         // LISTS
+        "li" if rewrite_note_send(element, state, list_item_start) => (),
         "ol" | "ul" | "menu" | "li" => {
-            let _ = handle_list_or_item_send(element, list_type, order_counter, list_item_start);
+            let _ = handle_list_or_item_send(element, state, options.style.bullet, list_item_start);
+        }
+        "span" => {
+            remove_backlink_send(element, state);
+        }
+        "input" => rewrite_checkbox_send(element, list_item_start),
+        "dl" | "dt" | "dd" => {
+            if commonmark && element_name == "dt" {
+                open_term_send(element, state);
+            }
+            rewrite_definition_element(
+                element,
+                commonmark,
+                options.style.strong.double(),
+                list_item_start,
+            );
         }
 
         "q" | "cite" | "blockquote" => {
@@ -289,16 +351,22 @@ pub fn handle_tag_send(
         }
 
         "div" | "section" | "header" | "footer" => {
+            if element_name == "div" {
+                let _ = handle_code_element_send(element, state, &quote_depth);
+            }
             insert_newline_before_send(element);
             insert_newline_after_send(element);
         }
         "pre" => {
-            element.before("\n```\n", Html);
-            element.after("\n```\n", Html);
+            let _ = handle_code_element_send(element, state, &quote_depth);
         }
         "code" | "samp" => {
-            element.before("`", Html);
-            element.after("`", Html);
+            let _ = handle_code_element_send(element, state, &quote_depth);
+        }
+        "html" | "body" | "title" | "svg" | "meta" | "link"
+            if is_capturing_metadata(&lock_state(state)) =>
+        {
+            handle_metadata_element_send(element, state, url);
         }
         _ => (),
     }
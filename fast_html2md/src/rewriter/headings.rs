@@ -0,0 +1,53 @@
+use super::lines::line_start_at;
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use lol_html::html_content::{ContentType::Text, Element, EndTag};
+
+/// The open setext heading wrote text, its underline is only written under the text.
+#[inline]
+pub(crate) fn capture_heading_text(state: &mut RewriterState, text: &str) {
+    if let Some(written) = state.heading.as_mut() {
+        *written |= !text.trim_ascii().is_empty();
+    }
+}
+
+/// The inline element of the open setext heading writes its markup.
+#[inline]
+pub(crate) fn capture_heading_element(state: &mut RewriterState, tag_name: &str) {
+    if let Some(written) = state.heading.as_mut() {
+        *written |= line_start_at(tag_name) == Some(false);
+    }
+}
+
+/// Open the setext heading, the underline is written at the end tag when the heading is not empty.
+pub(crate) fn open_setext_heading(el: &mut Element, state: &SharedState, underline: &'static str) {
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        state.borrow_mut().heading = Some(false);
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |end| {
+            if state.borrow_mut().heading.take() == Some(true) {
+                end.before(underline, Text);
+            }
+            Ok(())
+        }));
+    }
+}
+
+/// Open the setext heading, the underline is written at the end tag when the heading is not empty (send).
+pub(crate) fn open_setext_heading_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+    underline: &'static str,
+) {
+    if let Some(end_tag_handlers) = el.end_tag_handlers() {
+        lock_state(state).heading = Some(false);
+
+        let state = state.clone();
+        end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+            if lock_state(&state).heading.take() == Some(true) {
+                end.before(underline, Text);
+            }
+            Ok(())
+        }));
+    }
+}
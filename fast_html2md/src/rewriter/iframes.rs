@@ -1,12 +1,17 @@
 use crate::extended::base::iframe::{INSTAGRAM_PATTERN, VK_PATTERN, YOUTUBE_PATTERN};
+use crate::extended::base::url::resolve_url;
 use lol_html::html_content::ContentType::Text;
 use lol_html::html_content::Element;
+use url::Url;
 
 /// Handle the conversion to iframes.
 pub(crate) fn handle_iframe(
     element: &mut Element,
+    url: &Option<Url>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if let Some(src) = element.get_attribute("src") {
+        let src = resolve_url(url.as_ref(), src.into());
+
         if let Some(capture) = YOUTUBE_PATTERN.captures(&src) {
             let media_id = capture.get(1).map_or("", |m| m.as_str());
             element.replace(
@@ -29,7 +34,7 @@ pub(crate) fn handle_iframe(
             let owner_id = capture.get(1).map_or("", |m| m.as_str());
             let video_id = capture.get(2).map_or("", |m| m.as_str());
             element.replace(
-                &&format!("[![Embedded VK video](https://st.vk.com/images/icons/video_empty_2x.png)](https://vk.com/video{oid}_{vid})", oid = owner_id, vid = video_id),
+                &format!("[![Embedded VK video](https://st.vk.com/images/icons/video_empty_2x.png)](https://vk.com/video{oid}_{vid})", oid = owner_id, vid = video_id),
                 Text,
             );
             return Ok(());
@@ -42,8 +47,11 @@ pub(crate) fn handle_iframe(
 /// Handle the conversion to iframes.
 pub(crate) fn handle_iframe_send(
     element: &mut lol_html::send::Element,
+    url: &Option<Url>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if let Some(src) = element.get_attribute("src") {
+        let src = resolve_url(url.as_ref(), src.into());
+
         if let Some(capture) = YOUTUBE_PATTERN.captures(&src) {
             let media_id = capture.get(1).map_or("", |m| m.as_str());
             element.replace(
@@ -66,7 +74,7 @@ pub(crate) fn handle_iframe_send(
             let owner_id = capture.get(1).map_or("", |m| m.as_str());
             let video_id = capture.get(2).map_or("", |m| m.as_str());
             element.replace(
-                &&format!("[![Embedded VK video](https://st.vk.com/images/icons/video_empty_2x.png)](https://vk.com/video{oid}_{vid})", oid = owner_id, vid = video_id),
+                &format!("[![Embedded VK video](https://st.vk.com/images/icons/video_empty_2x.png)](https://vk.com/video{oid}_{vid})", oid = owner_id, vid = video_id),
                 Text,
             );
             return Ok(());
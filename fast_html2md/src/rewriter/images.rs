@@ -1,4 +1,9 @@
-use lol_html::html_content::Element;
+use super::decoded_attribute;
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use crate::extended::base::image::ImageSources;
+use crate::extended::base::url::{resolve_srcset, resolve_url};
+use crate::ImageSourcePolicy;
+use lol_html::html_content::{Element, EndTag};
 use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use std::borrow::Cow;
 use url::Url;
@@ -41,26 +46,110 @@ fn push_attr(s: &mut String, name: &str, value: &str) {
     s.push('"');
 }
 
+/// The `srcset` of a `<source>`.
+#[inline]
+fn source_srcset(srcset: Option<String>, data_srcset: Option<String>) -> Option<String> {
+    srcset
+        .or(data_srcset)
+        .filter(|srcset| !srcset.trim().is_empty())
+}
+
+/// Keep the first `<source>` of the open `<picture>`.
+#[inline]
+fn push_picture_source(state: &mut RewriterState, srcset: Option<String>) {
+    if let Some(picture @ None) = state.pictures.last_mut() {
+        *picture = srcset;
+    }
+}
+
+/// The `srcset` of the first `<source>` of the open `<picture>`.
+#[inline]
+pub(crate) fn picture_source(state: &RewriterState) -> Option<String> {
+    state.pictures.last().cloned().flatten()
+}
+
+/// Handle `<picture>` and its `<source>` elements, the image inside picks its url from them.
+pub(crate) fn handle_picture_element(el: &mut Element, state: &SharedState) {
+    if el.tag_name() == "picture" {
+        if let Some(end_tag_handlers) = el.end_tag_handlers() {
+            state.borrow_mut().pictures.push(None);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end| {
+                state.borrow_mut().pictures.pop();
+                Ok(())
+            }));
+        }
+    } else {
+        let srcset = source_srcset(
+            decoded_attribute(el, "srcset"),
+            decoded_attribute(el, "data-srcset"),
+        );
+        push_picture_source(&mut state.borrow_mut(), srcset);
+    }
+}
+
+/// Handle `<picture>` and its `<source>` elements (send).
+pub(crate) fn handle_picture_element_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+) {
+    if el.tag_name() == "picture" {
+        if let Some(end_tag_handlers) = el.end_tag_handlers() {
+            lock_state(state).pictures.push(None);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+                lock_state(&state).pictures.pop();
+                Ok(())
+            }));
+        }
+    } else {
+        let srcset = source_srcset(
+            decoded_attribute(el, "srcset"),
+            decoded_attribute(el, "data-srcset"),
+        );
+        push_picture_source(&mut lock_state(state), srcset);
+    }
+}
+
 /// Rewrite the image.
 pub(crate) fn rewrite_image_element(
     el: &mut Element,
     commonmark: bool,
     url: &Option<Url>,
+    policy: ImageSourcePolicy,
+    picture: Option<String>,
 ) -> Result<(), std::io::Error> {
-    let src = el.get_attribute("src").unwrap_or_default();
+    let sources = ImageSources::from_attributes(picture, |name| decoded_attribute(el, name));
+    let src = sources.select(policy).unwrap_or_default().to_string();
     let alt = el.get_attribute("alt").unwrap_or_default();
     let title = el.get_attribute("title").unwrap_or_default();
 
     let height = el.get_attribute("height");
     let width = el.get_attribute("width");
     let align = el.get_attribute("align");
+    let srcset = el.get_attribute("srcset");
 
     if commonmark && (height.is_some() || width.is_some() || align.is_some()) {
+        let src = resolve_url(
+            url.as_ref(),
+            Cow::Borrowed(sources.fallback().unwrap_or_default()),
+        );
         let mut img_tag = String::with_capacity(src.len() + 64);
         img_tag.push_str("<img src=\"");
-        img_tag.push_str(&src);
+        // the decoded url is written back as an attribute value
+        img_tag.push_str(&src.replace('&', "&amp;").replace('"', "&quot;"));
         img_tag.push('"');
 
+        if let Some(ref srcset) = srcset {
+            push_attr(
+                &mut img_tag,
+                "srcset",
+                &resolve_srcset(url.as_ref(), srcset),
+            );
+        }
+
         if !alt.is_empty() {
             push_attr(&mut img_tag, "alt", &alt);
         }
@@ -78,23 +167,14 @@ pub(crate) fn rewrite_image_element(
         }
 
         img_tag.push_str(" />");
-        el.set_inner_content(&img_tag, lol_html::html_content::ContentType::Html);
+        el.replace(&img_tag, lol_html::html_content::ContentType::Html);
     } else {
         let img_url: Cow<str> = if src.contains(' ') {
             Cow::Owned(utf8_percent_encode(&src, FRAGMENT).to_string())
-        } else if src.starts_with('/') {
-            if let Some(ref u) = url {
-                if let Ok(n) = u.join(&src) {
-                    Cow::Owned(n.to_string())
-                } else {
-                    Cow::Borrowed(&src)
-                }
-            } else {
-                Cow::Borrowed(&src)
-            }
         } else {
             Cow::Borrowed(&src)
         };
+        let img_url = resolve_url(url.as_ref(), img_url);
 
         el.replace(
             &build_image_markdown(&alt, &img_url, &title),
@@ -110,21 +190,38 @@ pub(crate) fn rewrite_image_element_send(
     el: &mut lol_html::send::Element,
     commonmark: bool,
     url: &Option<Url>,
+    policy: ImageSourcePolicy,
+    picture: Option<String>,
 ) -> Result<(), std::io::Error> {
-    let src = el.get_attribute("src").unwrap_or_default();
+    let sources = ImageSources::from_attributes(picture, |name| decoded_attribute(el, name));
+    let src = sources.select(policy).unwrap_or_default().to_string();
     let alt = el.get_attribute("alt").unwrap_or_default();
     let title = el.get_attribute("title").unwrap_or_default();
 
     let height = el.get_attribute("height");
     let width = el.get_attribute("width");
     let align = el.get_attribute("align");
+    let srcset = el.get_attribute("srcset");
 
     if commonmark && (height.is_some() || width.is_some() || align.is_some()) {
+        let src = resolve_url(
+            url.as_ref(),
+            Cow::Borrowed(sources.fallback().unwrap_or_default()),
+        );
         let mut img_tag = String::with_capacity(src.len() + 64);
         img_tag.push_str("<img src=\"");
-        img_tag.push_str(&src);
+        // the decoded url is written back as an attribute value
+        img_tag.push_str(&src.replace('&', "&amp;").replace('"', "&quot;"));
         img_tag.push('"');
 
+        if let Some(ref srcset) = srcset {
+            push_attr(
+                &mut img_tag,
+                "srcset",
+                &resolve_srcset(url.as_ref(), srcset),
+            );
+        }
+
         if !alt.is_empty() {
             push_attr(&mut img_tag, "alt", &alt);
         }
@@ -142,23 +239,14 @@ pub(crate) fn rewrite_image_element_send(
         }
 
         img_tag.push_str(" />");
-        el.set_inner_content(&img_tag, lol_html::html_content::ContentType::Html);
+        el.replace(&img_tag, lol_html::html_content::ContentType::Html);
     } else {
         let img_url: Cow<str> = if src.contains(' ') {
             Cow::Owned(utf8_percent_encode(&src, FRAGMENT).to_string())
-        } else if src.starts_with('/') {
-            if let Some(ref u) = url {
-                if let Ok(n) = u.join(&src) {
-                    Cow::Owned(n.to_string())
-                } else {
-                    Cow::Borrowed(&src)
-                }
-            } else {
-                Cow::Borrowed(&src)
-            }
         } else {
             Cow::Borrowed(&src)
         };
+        let img_url = resolve_url(url.as_ref(), img_url);
 
         el.replace(
             &build_image_markdown(&alt, &img_url, &title),
@@ -0,0 +1,113 @@
+/// Whether the text after the start tag begins a line: `Some(true)` after the line break of
+/// a block, `Some(false)` after inline markup, `None` when the element writes nothing.
+/// The text after the end tag of a block begins a line too.
+#[inline]
+pub(crate) fn line_start_at(tag_name: &str) -> Option<bool> {
+    match tag_name {
+        "p" | "div" | "section" | "header" | "footer" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+        | "br" | "hr" | "ul" | "ol" | "menu" | "li" | "dl" | "dt" | "dd" | "blockquote" | "pre"
+        | "table" | "tr" => Some(true),
+        "a" | "b" | "i" | "s" | "strong" | "em" | "del" | "code" | "samp" | "img" | "sub"
+        | "sup" | "input" | "q" | "cite" | "iframe" | "math" | "th" | "td" => Some(false),
+        _ => None,
+    }
+}
+
+/// The offset of the backslash escaping the block started by the text: a heading, a list
+/// marker, a setext underline, a table row or a code fence. The quotes, emphasis and
+/// tildes are escaped everywhere.
+#[inline]
+fn block_trigger(bytes: &[u8]) -> Option<usize> {
+    match bytes.first()? {
+        b'#' | b'+' | b'-' | b'=' | b'|' => Some(0),
+        b'`' if bytes.starts_with(b"```") => Some(0),
+        b'0'..=b'9' => {
+            let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+
+            (digits <= 9
+                && matches!(bytes.get(digits), Some(b'.' | b')'))
+                && matches!(bytes.get(digits + 1), None | Some(b' ' | b'\t' | b'\n')))
+            .then_some(digits)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the text at the end of a chunk may still start a block with the next chunk
+/// of its text: the digits of an ordered list marker or the start of a code fence.
+#[inline]
+fn partial_trigger(bytes: &[u8]) -> bool {
+    let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+
+    match bytes {
+        b"`" | b"``" => true,
+        _ if digits == 0 || digits > 9 => false,
+        _ => {
+            bytes.len() == digits
+                || (bytes.len() == digits + 1 && matches!(bytes[digits], b'.' | b')'))
+        }
+    }
+}
+
+/// Escape the sequences starting a block at the start of the lines of the text.
+///
+/// `line_start` tells whether the text begins a line, and is updated for the next text.
+/// A line start the chunk can not decide is held back in `held` and written with the next
+/// chunk of the text, the `last` chunk of the text writes it as is.
+/// Returns None if no changes needed (avoids allocation).
+#[inline]
+pub(crate) fn escape_line_starts(
+    text: &str,
+    line_start: &mut bool,
+    held: &mut String,
+    last: bool,
+) -> Option<String> {
+    let joined = !held.is_empty();
+    let text = if joined {
+        held.push_str(text);
+        std::borrow::Cow::Owned(std::mem::take(held))
+    } else {
+        std::borrow::Cow::Borrowed(text)
+    };
+    let bytes = text.as_bytes();
+    let mut output: Option<String> = None;
+    let mut copied = 0;
+    let mut end = text.len();
+    let mut at_start = *line_start;
+
+    for (index, &b) in bytes.iter().enumerate() {
+        match b {
+            b'\n' => at_start = true,
+            b' ' | b'\t' | b'\r' => (),
+            _ if at_start => {
+                if !last && partial_trigger(&bytes[index..]) {
+                    held.push_str(&text[index..]);
+                    end = index;
+                    break;
+                }
+
+                at_start = false;
+
+                if let Some(offset) = block_trigger(&bytes[index..]) {
+                    let output =
+                        output.get_or_insert_with(|| String::with_capacity(text.len() + 4));
+                    output.push_str(&text[copied..index + offset]);
+                    output.push('\\');
+                    copied = index + offset;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    *line_start = at_start;
+
+    if output.is_none() && !joined && end == text.len() {
+        return None;
+    }
+
+    let mut output = output.unwrap_or_default();
+    output.push_str(&text[copied..end]);
+
+    Some(output)
+}
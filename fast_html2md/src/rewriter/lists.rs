@@ -1,6 +1,10 @@
 use super::counter::Counter;
+use super::state::{lock_state, SharedState, SharedStateSend};
+use crate::extended::base::task::{checkbox_marker, is_checkbox};
+use crate::BulletMarker;
 use lol_html::html_content::ContentType;
 use lol_html::html_content::Element;
+use std::borrow::Cow;
 
 /// Pre-computed ordered list markers for common cases (1-20).
 /// Avoids format! allocation for the most common list lengths.
@@ -12,44 +16,108 @@ const OL_MARKERS: [&str; 21] = [
 
 /// Get ordered list marker, using pre-computed for common cases.
 #[inline]
-fn get_ol_marker(n: usize) -> std::borrow::Cow<'static, str> {
+fn get_ol_marker(n: usize) -> Cow<'static, str> {
     if n < OL_MARKERS.len() {
-        std::borrow::Cow::Borrowed(OL_MARKERS[n])
+        Cow::Borrowed(OL_MARKERS[n])
     } else {
-        std::borrow::Cow::Owned(format!("\n{}. ", n))
+        Cow::Owned(format!("\n{}. ", n))
+    }
+}
+
+/// An open `<ul>`, `<ol>` or `<menu>` list.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ListFrame {
+    /// The list is numbered.
+    ordered: bool,
+    /// The number of the last item.
+    counter: usize,
+    /// The width of the last marker, nested lists are indented under it.
+    marker_width: usize,
+}
+
+impl ListFrame {
+    /// A new list frame.
+    fn new(ordered: bool) -> Self {
+        Self {
+            ordered,
+            ..Default::default()
+        }
+    }
+}
+
+/// The marker of an unordered item of a top level list.
+#[inline]
+fn get_ul_marker(bullet: BulletMarker) -> &'static str {
+    match bullet {
+        BulletMarker::Asterisk => "\n* ",
+        BulletMarker::Dash => "\n- ",
+        BulletMarker::Plus => "\n+ ",
+    }
+}
+
+/// Build the marker of the next item of the innermost list, indented under the
+/// content of the parent items.
+#[inline]
+pub(crate) fn next_item_marker(lists: &mut [ListFrame], bullet: BulletMarker) -> Cow<'static, str> {
+    let indent: usize = lists.iter().rev().skip(1).map(|f| f.marker_width).sum();
+
+    match lists.last_mut() {
+        Some(frame) if frame.ordered => {
+            let order = frame.counter.increment();
+            frame.marker_width = order.checked_ilog10().unwrap_or_default() as usize + 3;
+
+            if indent == 0 {
+                get_ol_marker(order)
+            } else {
+                Cow::Owned(format!("\n{:indent$}{}. ", "", order))
+            }
+        }
+        frame => {
+            if let Some(frame) = frame {
+                frame.marker_width = 2;
+            }
+
+            if indent == 0 {
+                Cow::Borrowed(get_ul_marker(bullet))
+            } else {
+                Cow::Owned(format!("\n{:indent$}{} ", "", bullet.as_str()))
+            }
+        }
     }
 }
 
 /// Function to handle list elements and items
 ///
+/// Lists push a frame on the list stack that is popped by their end tag, restoring
+/// the numbering and type of the outer list.
+///
 /// IMPORTANT: `list_item_start` is set to true when we emit a list marker.
 /// The tag handler uses it to avoid inserting a newline before the first <p>
 /// and the text handler can drop whitespace-only nodes while this is true.
 #[inline]
 pub(crate) fn handle_list_or_item(
     element: &mut Element,
-    list_type: &mut Option<&'static str>,
-    order_counter: &mut usize,
+    state: &SharedState,
+    bullet: BulletMarker,
     list_item_start: &mut bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     match element.tag_name().as_str() {
-        "ul" | "menu" => {
-            *list_type = Some("ul");
-            order_counter.reset();
-        }
-        "ol" => {
-            *list_type = Some("ol");
-            order_counter.reset();
+        tag @ ("ul" | "menu" | "ol") => {
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                state.borrow_mut().lists.push(ListFrame::new(tag == "ol"));
+
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(move |_end| {
+                    state.borrow_mut().lists.pop();
+                    Ok(())
+                }));
+            }
         }
         "li" => {
             *list_item_start = true;
 
-            if *list_type == Some("ol") {
-                let order = order_counter.increment();
-                element.before(&get_ol_marker(order), ContentType::Text);
-            } else {
-                element.before("\n* ", ContentType::Text);
-            }
+            let marker = next_item_marker(&mut state.borrow_mut().lists, bullet);
+            element.before(&marker, ContentType::Text);
         }
         _ => (),
     }
@@ -57,32 +125,68 @@ pub(crate) fn handle_list_or_item(
     Ok(())
 }
 
+/// Rewrite a checkbox `<input>`: a task `[x] ` at the start of a list item, `[x]` elsewhere.
+#[inline]
+pub(crate) fn rewrite_checkbox(element: &mut Element, list_item_start: &mut bool) {
+    if !is_checkbox(element.get_attribute("type").as_deref()) {
+        return;
+    }
+
+    let marker = checkbox_marker(element.has_attribute("checked"));
+
+    if *list_item_start {
+        element.before(&format!("{marker} "), ContentType::Text);
+    } else {
+        element.before(marker, ContentType::Text);
+    }
+}
+
+/// Rewrite a checkbox `<input>`: a task `[x] ` at the start of a list item, `[x]` elsewhere (send).
+#[inline]
+pub(crate) fn rewrite_checkbox_send(
+    element: &mut lol_html::send::Element,
+    list_item_start: &mut bool,
+) {
+    if !is_checkbox(element.get_attribute("type").as_deref()) {
+        return;
+    }
+
+    let marker = checkbox_marker(element.has_attribute("checked"));
+
+    if *list_item_start {
+        element.before(&format!("{marker} "), ContentType::Text);
+    } else {
+        element.before(marker, ContentType::Text);
+    }
+}
+
 /// Function to handle list elements and items (send)
 #[inline]
 pub(crate) fn handle_list_or_item_send(
     element: &mut lol_html::send::Element,
-    list_type: &mut Option<&'static str>,
-    order_counter: &mut usize,
+    state: &SharedStateSend,
+    bullet: BulletMarker,
     list_item_start: &mut bool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     match element.tag_name().as_str() {
-        "ul" | "menu" => {
-            *list_type = Some("ul");
-            order_counter.reset();
-        }
-        "ol" => {
-            *list_type = Some("ol");
-            order_counter.reset();
+        tag @ ("ul" | "menu" | "ol") => {
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                lock_state(state).lists.push(ListFrame::new(tag == "ol"));
+
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(
+                    move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                        lock_state(&state).lists.pop();
+                        Ok(())
+                    },
+                ));
+            }
         }
         "li" => {
             *list_item_start = true;
 
-            if *list_type == Some("ol") {
-                let order = order_counter.increment();
-                element.before(&get_ol_marker(order), ContentType::Text);
-            } else {
-                element.before("\n* ", ContentType::Text);
-            }
+            let marker = next_item_marker(&mut lock_state(state).lists, bullet);
+            element.before(&marker, ContentType::Text);
         }
         _ => (),
     }
@@ -0,0 +1,147 @@
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use crate::extended::base::math::{is_math_rendering, math_script_display, MathBuilder};
+use lol_html::html_content::{ContentType, Element, EndTag};
+
+/// The attributes of the element.
+#[inline]
+fn element_attributes<H: lol_html::HandlerTypes>(el: &Element<'_, '_, H>) -> Vec<(String, String)> {
+    el.attributes()
+        .iter()
+        .map(|attribute| (attribute.name(), attribute.value()))
+        .collect()
+}
+
+/// The element is read as math: a `<math>`, a MathJax script or their content.
+#[inline]
+fn is_math(state: &RewriterState, tag_name: &str, script_type: Option<String>) -> bool {
+    state.math.is_some()
+        || tag_name == "math"
+        || (tag_name == "script"
+            && script_type
+                .as_deref()
+                .and_then(math_script_display)
+                .is_some())
+}
+
+/// Open the element in the math being read.
+#[inline]
+fn open_math(state: &mut RewriterState, tag_name: &str, attributes: Vec<(String, String)>) {
+    state
+        .math
+        .get_or_insert_with(MathBuilder::default)
+        .open(tag_name, attributes);
+}
+
+/// Close the element, the markdown of the math once its root is closed.
+#[inline]
+fn close_math(state: &mut RewriterState) -> Option<String> {
+    let math = state.math.as_mut()?;
+    math.close();
+
+    if math.is_done() {
+        state.math.take()?.into_markdown()
+    } else {
+        None
+    }
+}
+
+/// Read the text of the open math.
+#[inline]
+pub(crate) fn capture_math_text(state: &mut RewriterState, text: &str) -> bool {
+    match state.math.as_mut() {
+        Some(math) => {
+            let decoded = crate::decode_text_opt(text);
+            math.text(decoded.as_deref().unwrap_or(text));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Read the TeX of the open MathJax script, the text of a script is not escaped.
+#[inline]
+pub(crate) fn capture_math_script_text(state: &mut RewriterState, text: &str) {
+    if let Some(math) = state.math.as_mut() {
+        math.text(text);
+    }
+}
+
+/// Read the `<math>` elements and the MathJax scripts, written as `$…$` at their end tag.
+/// The KaTeX and MathJax renderings of the math are dropped.
+pub(crate) fn handle_math_element(el: &mut Element, state: &SharedState) -> bool {
+    let tag_name = el.tag_name();
+
+    if state.borrow().math.is_none()
+        && is_math_rendering(&tag_name, el.get_attribute("class").as_deref())
+    {
+        el.remove();
+        return true;
+    }
+
+    if !is_math(&state.borrow(), &tag_name, el.get_attribute("type")) {
+        return false;
+    }
+
+    open_math(&mut state.borrow_mut(), &tag_name, element_attributes(el));
+
+    match el.end_tag_handlers() {
+        Some(end_tag_handlers) => {
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |end| {
+                if let Some(markdown) = close_math(&mut state.borrow_mut()) {
+                    end.after(&markdown, ContentType::Html);
+                }
+                Ok(())
+            }));
+        }
+        // a self-closing element
+        None => {
+            if let Some(markdown) = close_math(&mut state.borrow_mut()) {
+                el.after(&markdown, ContentType::Html);
+            }
+        }
+    }
+
+    true
+}
+
+/// Read the `<math>` elements and the MathJax scripts, written as `$…$` at their end tag (send).
+/// The KaTeX and MathJax renderings of the math are dropped.
+pub(crate) fn handle_math_element_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+) -> bool {
+    let tag_name = el.tag_name();
+
+    if lock_state(state).math.is_none()
+        && is_math_rendering(&tag_name, el.get_attribute("class").as_deref())
+    {
+        el.remove();
+        return true;
+    }
+
+    if !is_math(&lock_state(state), &tag_name, el.get_attribute("type")) {
+        return false;
+    }
+
+    open_math(&mut lock_state(state), &tag_name, element_attributes(el));
+
+    match el.end_tag_handlers() {
+        Some(end_tag_handlers) => {
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+                if let Some(markdown) = close_math(&mut lock_state(&state)) {
+                    end.after(&markdown, ContentType::Html);
+                }
+                Ok(())
+            }));
+        }
+        None => {
+            if let Some(markdown) = close_math(&mut lock_state(state)) {
+                el.after(&markdown, ContentType::Html);
+            }
+        }
+    }
+
+    true
+}
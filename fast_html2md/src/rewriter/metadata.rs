@@ -0,0 +1,275 @@
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use crate::extended::base::url::resolve_url;
+use lol_html::html_content::{Element, EndTag};
+use std::borrow::Cow;
+use url::Url;
+
+/// The `<head>` metadata written as the YAML front matter.
+#[derive(Debug, Default)]
+pub(crate) struct Metadata {
+    /// The `<title>`.
+    title: Option<String>,
+    /// The `<meta name="description">`.
+    description: Option<String>,
+    /// The `<html lang>`.
+    lang: Option<String>,
+    /// The `<link rel="canonical">`.
+    canonical: Option<String>,
+    /// The `og:*` and `twitter:*` meta tags in document order, the first of each name is kept.
+    properties: Vec<(String, String)>,
+    /// The `<title>` of the `<head>` is open, its text is not part of the body.
+    in_title: bool,
+    /// The first `<title>` was read.
+    title_read: bool,
+    /// The `<body>` was opened, a later `<title>` is not the title of the document.
+    in_body: bool,
+    /// The depth of the open `<svg>` elements, their `<title>` labels the image.
+    svg_depth: usize,
+}
+
+impl Metadata {
+    /// Write the YAML front matter, nothing when no metadata was found.
+    pub(crate) fn front_matter(&self) -> String {
+        let fields = [
+            ("title", &self.title),
+            ("description", &self.description),
+            ("lang", &self.lang),
+            ("canonical", &self.canonical),
+        ];
+        let fields = fields
+            .iter()
+            .filter_map(|(name, value)| Some((*name, value.as_deref()?)))
+            .chain(
+                self.properties
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str())),
+            );
+
+        let mut out = String::new();
+
+        for (name, value) in fields {
+            out.push_str(name);
+            out.push_str(": ");
+            push_yaml_string(&mut out, value);
+            out.push('\n');
+        }
+
+        if !out.is_empty() {
+            out.insert_str(0, "---\n");
+            out.push_str("---\n\n");
+        }
+
+        out
+    }
+}
+
+/// Write the value as a double quoted YAML string.
+fn push_yaml_string(out: &mut String, value: &str) {
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Collapse the whitespace of the value, empty values are dropped.
+#[inline]
+fn clean_value(value: &str) -> Option<String> {
+    let value = crate::decode_text_opt(value).unwrap_or_else(|| value.to_string());
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    (!value.is_empty()).then_some(value)
+}
+
+/// The front matter is captured.
+#[inline]
+pub(crate) fn is_capturing_metadata(state: &RewriterState) -> bool {
+    state.metadata.is_some()
+}
+
+/// Capture the text of the open `<title>`, returns true when the text is dropped from the body.
+#[inline]
+pub(crate) fn capture_title_text(state: &mut RewriterState, text: &str) -> bool {
+    match state.metadata.as_mut().filter(|metadata| metadata.in_title) {
+        Some(metadata) => {
+            if !metadata.title_read {
+                metadata
+                    .title
+                    .get_or_insert_with(String::new)
+                    .push_str(text);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// The front matter of the document.
+#[inline]
+pub(crate) fn front_matter(state: &RewriterState) -> String {
+    state
+        .metadata
+        .as_ref()
+        .map(Metadata::front_matter)
+        .unwrap_or_default()
+}
+
+/// Read the metadata of a `<meta>` tag.
+#[inline]
+fn push_meta(metadata: &mut Metadata, name: Option<String>, content: Option<String>) {
+    let (Some(name), Some(content)) = (name, content.as_deref().and_then(clean_value)) else {
+        return;
+    };
+    let name = name.trim().to_ascii_lowercase();
+
+    if name == "description" {
+        metadata.description.get_or_insert(content);
+    } else if (name.starts_with("og:") || name.starts_with("twitter:"))
+        && !metadata.properties.iter().any(|(known, _)| *known == name)
+    {
+        metadata.properties.push((name, content));
+    }
+}
+
+/// Open or close the `<title>`, the first one of the `<head>` is the title of the document.
+#[inline]
+fn set_in_title(state: &mut RewriterState, in_title: bool) {
+    if let Some(metadata) = state.metadata.as_mut() {
+        if in_title {
+            metadata.in_title = !metadata.in_body && metadata.svg_depth == 0;
+        } else if metadata.in_title {
+            metadata.in_title = false;
+
+            if !metadata.title_read {
+                metadata.title = metadata.title.as_deref().and_then(clean_value);
+                metadata.title_read = true;
+            }
+        }
+    }
+}
+
+/// Open or close an `<svg>`.
+#[inline]
+fn set_in_svg(state: &mut RewriterState, in_svg: bool) {
+    if let Some(metadata) = state.metadata.as_mut() {
+        if in_svg {
+            metadata.svg_depth += 1;
+        } else {
+            metadata.svg_depth = metadata.svg_depth.saturating_sub(1);
+        }
+    }
+}
+
+/// Read the metadata of the element.
+#[inline]
+fn push_element(
+    state: &mut RewriterState,
+    url: &Option<Url>,
+    tag_name: &str,
+    attribute: impl Fn(&str) -> Option<String>,
+) {
+    let base = state.base_url(url).clone();
+
+    let Some(metadata) = state.metadata.as_mut() else {
+        return;
+    };
+
+    match tag_name {
+        "body" => metadata.in_body = true,
+        "html" if metadata.lang.is_none() => {
+            metadata.lang = attribute("lang").as_deref().and_then(clean_value);
+        }
+        "meta" => push_meta(
+            metadata,
+            attribute("name").or_else(|| attribute("property")),
+            attribute("content"),
+        ),
+        "link" => {
+            let canonical = attribute("rel").is_some_and(|rel| {
+                rel.split_ascii_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("canonical"))
+            });
+
+            if canonical && metadata.canonical.is_none() {
+                metadata.canonical = attribute("href")
+                    .map(|href| resolve_url(base.as_ref(), Cow::Owned(href)).into_owned());
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Capture the `<head>` metadata of the element for the front matter.
+pub(crate) fn handle_metadata_element(el: &mut Element, state: &SharedState, url: &Option<Url>) {
+    let tag_name = el.tag_name();
+
+    if tag_name == "title" {
+        if let Some(end_tag_handlers) = el.end_tag_handlers() {
+            set_in_title(&mut state.borrow_mut(), true);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end| {
+                set_in_title(&mut state.borrow_mut(), false);
+                Ok(())
+            }));
+        }
+    } else if tag_name == "svg" {
+        if let Some(end_tag_handlers) = el.end_tag_handlers() {
+            set_in_svg(&mut state.borrow_mut(), true);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end| {
+                set_in_svg(&mut state.borrow_mut(), false);
+                Ok(())
+            }));
+        }
+    } else {
+        push_element(&mut state.borrow_mut(), url, &tag_name, |name| {
+            el.get_attribute(name)
+        });
+    }
+}
+
+/// Capture the `<head>` metadata of the element for the front matter (send).
+pub(crate) fn handle_metadata_element_send(
+    el: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+    url: &Option<Url>,
+) {
+    let tag_name = el.tag_name();
+
+    if tag_name == "title" {
+        if let Some(end_tag_handlers) = el.end_tag_handlers() {
+            set_in_title(&mut lock_state(state), true);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+                set_in_title(&mut lock_state(&state), false);
+                Ok(())
+            }));
+        }
+    } else if tag_name == "svg" {
+        if let Some(end_tag_handlers) = el.end_tag_handlers() {
+            set_in_svg(&mut lock_state(state), true);
+
+            let state = state.clone();
+            end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+                set_in_svg(&mut lock_state(&state), false);
+                Ok(())
+            }));
+        }
+    } else {
+        push_element(&mut lock_state(state), url, &tag_name, |name| {
+            el.get_attribute(name)
+        });
+    }
+}
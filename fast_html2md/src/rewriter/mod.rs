@@ -1,13 +1,36 @@
 pub(crate) mod anchors;
+pub(crate) mod codes;
 pub(crate) mod counter;
+pub mod custom;
+pub(crate) mod definitions;
+pub mod extract;
+pub(crate) mod footnotes;
 pub(crate) mod handle;
+pub(crate) mod headings;
 pub(crate) mod iframes;
 pub(crate) mod images;
+pub(crate) mod lines;
 pub(crate) mod lists;
+pub(crate) mod math;
+pub(crate) mod metadata;
 pub(crate) mod quotes;
+pub(crate) mod references;
+pub(crate) mod state;
 pub(crate) mod styles;
+pub(crate) mod tables;
 pub mod writer;
 
+/// The value of the attribute with its character references decoded, lol_html keeps them as written.
+#[inline]
+pub(crate) fn decoded_attribute<H: lol_html::HandlerTypes>(
+    element: &lol_html::html_content::Element<'_, '_, H>,
+    name: &str,
+) -> Option<String> {
+    element
+        .get_attribute(name)
+        .map(|value| crate::decode_text_opt(&value).unwrap_or(value))
+}
+
 /// Insert a new line after
 #[inline]
 pub(crate) fn insert_newline_after(element: &mut lol_html::html_content::Element) {
@@ -16,6 +16,20 @@ fn get_quote_prefix(depth: usize) -> std::borrow::Cow<'static, str> {
     }
 }
 
+/// Prefix every line of the markdown written inside the quote.
+#[inline]
+pub(crate) fn quote_lines(markdown: &str, depth: usize) -> String {
+    let quote_prefix = get_quote_prefix(depth);
+    let mut out = String::with_capacity(markdown.len() + quote_prefix.len() * 4);
+
+    for line in markdown.split_inclusive('\n') {
+        out.push_str(&quote_prefix);
+        out.push_str(line);
+    }
+
+    out
+}
+
 // Function to handle <blockquote> elements
 pub(crate) fn rewrite_blockquote_element(
     el: &mut Element,
@@ -0,0 +1,46 @@
+use crate::extended::base::entity::LONGEST_NAMED_ENTITY;
+
+/// The start of the character reference left open at the end of the text: `&eac` of `t&eac`.
+/// A reference followed by the start of a name is open too, the decoded `&` of `&amp;l`
+/// is escaped when a reference follows it.
+#[inline]
+fn open_reference(text: &str) -> Option<usize> {
+    let start = text.rfind('&')?;
+    let tail = &text.as_bytes()[start + 1..];
+    let (name, rest) = match tail.iter().position(|b| *b == b';') {
+        Some(end) => (&tail[..end], &tail[end + 1..]),
+        None => (tail, &[][..]),
+    };
+    let is_name = |bytes: &[u8]| {
+        bytes.len() <= LONGEST_NAMED_ENTITY + 1
+            && bytes
+                .iter()
+                .all(|b| b.is_ascii_alphanumeric() || *b == b'#')
+    };
+
+    (is_name(name) && is_name(rest)).then_some(start)
+}
+
+/// Join the character references split by the chunks of the text.
+///
+/// The reference left open at the end of a chunk is held back in `held` and decoded with
+/// the next chunk of the text, the `last` chunk of the text writes it as is.
+/// Returns None if the text is unchanged.
+#[inline]
+pub(crate) fn join_references(text: &str, held: &mut String, last: bool) -> Option<String> {
+    if held.is_empty() && (last || open_reference(text).is_none()) {
+        return None;
+    }
+
+    let mut joined = std::mem::take(held);
+    joined.push_str(text);
+
+    if !last {
+        if let Some(start) = open_reference(&joined) {
+            held.push_str(&joined[start..]);
+            joined.truncate(start);
+        }
+    }
+
+    Some(joined)
+}
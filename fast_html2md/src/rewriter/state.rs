@@ -0,0 +1,113 @@
+use super::codes::CodeBlock;
+use super::definitions::Term;
+use super::extract::Extraction;
+use super::footnotes::FootnoteState;
+use super::lists::ListFrame;
+use super::metadata::Metadata;
+use super::tables::TableFrame;
+use crate::extended::base::link::LinkReferences;
+use crate::extended::base::math::MathBuilder;
+use crate::extended::base::url::document_base_url;
+use crate::{FenceMarker, LinkStyle};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use url::Url;
+
+/// Structural state of the document being rewritten.
+///
+/// The element handlers open the frames and the end tag handlers close them,
+/// so the state is shared between both.
+#[derive(Debug, Default)]
+pub(crate) struct RewriterState {
+    /// The open lists, innermost last.
+    pub(crate) lists: Vec<ListFrame>,
+    /// The open tables, innermost last.
+    pub(crate) tables: Vec<TableFrame>,
+    /// The empty cells missing from the header row of each delimited table, in document order.
+    pub(crate) table_padding: Vec<usize>,
+    /// The open `<pre>` block.
+    pub(crate) code: Option<CodeBlock>,
+    /// The text of the open inline `<code>`.
+    pub(crate) code_span: Option<String>,
+    /// The code language declared by an enclosing element.
+    pub(crate) code_hint: Option<String>,
+    /// The character of the code fences.
+    pub(crate) code_fence: FenceMarker,
+    /// The open `<picture>` elements with the `srcset` of their first `<source>`, innermost last.
+    pub(crate) pictures: Vec<Option<String>>,
+    /// The base url declared by `<base href>`.
+    pub(crate) base_url: Option<Url>,
+    /// The urls of the reference links.
+    pub(crate) references: LinkReferences,
+    /// The links and images collected alongside the markdown.
+    pub(crate) extraction: Option<Extraction>,
+    /// The `<head>` metadata of the front matter.
+    pub(crate) metadata: Option<Metadata>,
+    /// The footnotes of the citations.
+    pub(crate) footnotes: FootnoteState,
+    /// The open `<math>` or MathJax script.
+    pub(crate) math: Option<MathBuilder>,
+    /// The open strong term of a definition list.
+    pub(crate) term: Option<Term>,
+    /// The open setext heading and whether it wrote text.
+    pub(crate) heading: Option<bool>,
+    /// The start of a line held back until the next chunk of the text.
+    pub(crate) line_start: String,
+    /// The character reference held back until the next chunk of the text.
+    pub(crate) reference: String,
+}
+
+/// The `<base href>` of the document or the url of the options.
+#[inline]
+fn document_url<'a>(base_url: &'a Option<Url>, url: &'a Option<Url>) -> &'a Option<Url> {
+    if base_url.is_some() {
+        base_url
+    } else {
+        url
+    }
+}
+
+impl RewriterState {
+    /// The base of the relative urls: the `<base href>` of the document or the url of the options.
+    #[inline]
+    pub(crate) fn base_url<'a>(&'a self, url: &'a Option<Url>) -> &'a Option<Url> {
+        document_url(&self.base_url, url)
+    }
+
+    /// The base of the relative urls and the reference links, when links are written as references.
+    #[inline]
+    pub(crate) fn link_targets<'a>(
+        &'a mut self,
+        url: &'a Option<Url>,
+        link_style: LinkStyle,
+    ) -> (&'a Option<Url>, Option<&'a mut LinkReferences>) {
+        (
+            document_url(&self.base_url, url),
+            (link_style == LinkStyle::Referenced).then_some(&mut self.references),
+        )
+    }
+
+    /// Use the first `<base href>` of the document as the base url.
+    #[inline]
+    pub(crate) fn set_base_url(&mut self, url: &Option<Url>, href: Option<String>) {
+        if self.base_url.is_none() {
+            if let Some(href) = href {
+                self.base_url = document_base_url(url.as_ref(), &href);
+            }
+        }
+    }
+}
+
+/// Shared rewriter state (sync).
+pub(crate) type SharedState = Rc<RefCell<RewriterState>>;
+
+/// Shared rewriter state (send).
+pub(crate) type SharedStateSend = Arc<Mutex<RewriterState>>;
+
+/// Lock the send state. A panic inside a handler can not leave the state half updated
+/// in a way that matters for the output, so a poisoned lock is recovered.
+#[inline]
+pub(crate) fn lock_state(state: &SharedStateSend) -> MutexGuard<'_, RewriterState> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -1,12 +1,16 @@
+use crate::MarkdownStyle;
 use lol_html::html_content::{ContentType::Text, Element};
 
 /// Rewrite the initial elements that need extra styles.
-pub(crate) fn rewrite_style_element(el: &mut Element) -> Result<(), std::io::Error> {
+pub(crate) fn rewrite_style_element(
+    el: &mut Element,
+    style: &MarkdownStyle,
+) -> Result<(), std::io::Error> {
     let tag_name = el.tag_name();
 
     let mark = match tag_name.as_str() {
-        "b" | "strong" => "**",
-        "i" | "em" => "*",
+        "b" | "strong" => style.strong.double(),
+        "i" | "em" => style.emphasis.single(),
         "s" | "del" => "~~",
         "u" | "ins" => "__",
         _ => return Ok(()), // Return early if tag is not one of the specified
@@ -21,12 +25,13 @@ pub(crate) fn rewrite_style_element(el: &mut Element) -> Result<(), std::io::Err
 /// Rewrite the initial elements that need extra styles.
 pub(crate) fn rewrite_style_element_send(
     el: &mut lol_html::send::Element,
+    style: &MarkdownStyle,
 ) -> Result<(), std::io::Error> {
     let tag_name = el.tag_name();
 
     let mark = match tag_name.as_str() {
-        "b" | "strong" => "**",
-        "i" | "em" => "*",
+        "b" | "strong" => style.strong.double(),
+        "i" | "em" => style.emphasis.single(),
         "s" | "del" => "~~",
         "u" | "ins" => "__",
         _ => return Ok(()), // Return early if tag is not one of the specified
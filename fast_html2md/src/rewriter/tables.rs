@@ -0,0 +1,588 @@
+use super::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use super::{insert_newline_after, insert_newline_after_send};
+use crate::extended::base::table::{
+    parse_span, resolve_alignments, Alignment, ColumnGroup, RowSpans,
+};
+use crate::{ConversionOptions, SpanPolicy};
+use lol_html::html_content::{
+    ContentType::{Html, Text},
+    Element, EndTag,
+};
+
+/// An open `<table>`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TableFrame {
+    /// The rows started so far.
+    rows: usize,
+    /// The columns of the header row.
+    columns: usize,
+    /// The header delimiter row was written.
+    delimited: bool,
+    /// The index of the table in the padding of the delimited tables.
+    padding: Option<usize>,
+    /// The columns of the widest row.
+    widest: usize,
+    /// The alignment of each header cell column.
+    header: Vec<Alignment>,
+    /// The column alignments declared by `<col>` and `<colgroup>`.
+    column_alignments: Vec<Alignment>,
+    /// The open `<colgroup>`.
+    group: Option<ColumnGroup>,
+    /// The column of the next cell in the current row.
+    column: usize,
+    /// The cells of previous rows covering the columns through their `rowspan`.
+    spans: RowSpans,
+    /// The covered cells repeat the spanning cell.
+    repeat: bool,
+    /// The text of the open spanning cell, captured to be repeated.
+    cell_text: Option<String>,
+}
+
+impl TableFrame {
+    /// A new table frame.
+    fn new(span_policy: SpanPolicy) -> Self {
+        Self {
+            repeat: span_policy == SpanPolicy::Repeat,
+            ..Default::default()
+        }
+    }
+
+    /// Take the header delimiter row if it is still due: `\n|---|:-:|`.
+    fn take_delimiter(&mut self, padding: &mut Vec<usize>) -> Option<String> {
+        if self.delimited || self.columns == 0 {
+            return None;
+        }
+
+        self.delimited = true;
+        self.padding = Some(padding.len());
+        padding.push(0);
+
+        let mut row = String::with_capacity(2 + self.columns * 6);
+        row.push_str("\n|");
+        // the cells are not padded, the columns have the width of the narrowest delimiter
+        for alignment in resolve_alignments(&self.header, &self.column_alignments, self.columns) {
+            alignment.push_delimiter(&mut row, 3);
+            row.push('|');
+        }
+
+        Some(row)
+    }
+
+    /// Close the open `<colgroup>`.
+    fn finish_group(&mut self) {
+        if let Some(group) = self.group.take() {
+            group.finish(&mut self.column_alignments);
+        }
+    }
+
+    /// Open a `<colgroup>`.
+    fn open_group(&mut self, alignment: Alignment, span: usize) {
+        self.finish_group();
+        self.group = Some(ColumnGroup::new(alignment, span));
+    }
+
+    /// Add a `<col>`.
+    fn push_col(&mut self, alignment: Alignment, span: usize) {
+        match self.group.as_mut() {
+            Some(group) => group.push_col(&mut self.column_alignments, alignment, span),
+            None => self
+                .column_alignments
+                .extend(std::iter::repeat_n(alignment, span)),
+        }
+    }
+
+    /// Write a cell covered by a span.
+    fn push_covered(&self, out: &mut String, text: &str) {
+        if self.repeat {
+            out.push_str(text);
+        }
+        out.push('|');
+    }
+
+    /// Start a row, the delimiter is returned when the header row was not closed.
+    fn start_row(&mut self, padding: &mut Vec<usize>) -> Option<String> {
+        self.finish_group();
+
+        let delimiter = if self.rows == 1 {
+            self.take_delimiter(padding)
+        } else {
+            None
+        };
+
+        self.rows += 1;
+        self.column = 0;
+
+        delimiter
+    }
+
+    /// End a row: the columns after the last cell still covered by a previous row,
+    /// then the delimiter after the header row.
+    fn end_row(&mut self, padding: &mut Vec<usize>) -> String {
+        let mut out = String::new();
+        let mut covered_len = 0;
+        let mut width = self.column;
+        let row_started = self.column > 0;
+
+        while self.column < self.spans.len() {
+            match self.spans.take(self.column) {
+                Some(text) => {
+                    let text = text.to_string();
+                    self.push_covered(&mut out, &text);
+                    covered_len = out.len();
+                    width = self.column + 1;
+                }
+                None => out.push('|'),
+            }
+            self.column += 1;
+        }
+
+        out.truncate(covered_len);
+        self.widest = self.widest.max(width);
+
+        if !row_started && !out.is_empty() {
+            out.insert(0, '|');
+        }
+
+        if self.rows == 1 {
+            if let Some(delimiter) = self.take_delimiter(padding) {
+                out.push_str(&delimiter);
+            }
+        }
+
+        out
+    }
+
+    /// Close the table, the header row is padded to the widest row.
+    fn finish(&self, padding: &mut [usize]) {
+        if let Some(pad) = self.padding.and_then(|index| padding.get_mut(index)) {
+            *pad = self.widest.saturating_sub(self.columns);
+        }
+    }
+
+    /// Start a cell, the cells covered by previous rows before it are returned.
+    fn start_cell(&mut self, alignment: Alignment, colspan: usize, rowspan: usize) -> String {
+        let mut out = String::new();
+
+        while let Some(text) = self.spans.take(self.column) {
+            let text = text.to_string();
+            self.push_covered(&mut out, &text);
+            self.column += 1;
+        }
+
+        if self.rows == 1 {
+            self.columns += colspan;
+            self.header.extend(std::iter::repeat_n(alignment, colspan));
+        }
+
+        if self.repeat && (colspan > 1 || rowspan > 1) {
+            self.cell_text = Some(String::new());
+        } else {
+            self.spans.insert(self.column, colspan, rowspan, "");
+        }
+
+        self.column += colspan;
+
+        out
+    }
+
+    /// End a spanning cell: the closer of its text, then the cells covered by its `colspan`.
+    fn end_cell(&mut self, column: usize, colspan: usize, rowspan: usize, closer: &str) -> String {
+        let text = self.cell_text.take().unwrap_or_default();
+
+        if self.repeat {
+            self.spans.insert(column, colspan, rowspan, &text);
+        }
+
+        let mut out = String::from(closer);
+        for _ in 1..colspan {
+            out.push('|');
+            if self.repeat {
+                out.push_str(&text);
+            }
+        }
+
+        out
+    }
+}
+
+/// Capture the text of the open spanning cell.
+#[inline]
+pub(crate) fn capture_cell_text(state: &mut RewriterState, text: &str) {
+    if let Some(cell_text) = state
+        .tables
+        .last_mut()
+        .and_then(|frame| frame.cell_text.as_mut())
+    {
+        cell_text.push_str(text);
+    }
+}
+
+/// The alignment declared on the element.
+#[inline]
+fn element_alignment(align: Option<String>, style: Option<String>) -> Alignment {
+    Alignment::from_attributes(align.as_deref(), style.as_deref())
+}
+
+/// The innermost open table and the padding of the delimited tables.
+#[inline]
+fn open_table(state: &mut RewriterState) -> Option<(&mut TableFrame, &mut Vec<usize>)> {
+    let padding = &mut state.table_padding;
+    state.tables.last_mut().map(|frame| (frame, padding))
+}
+
+/// Write the delimiter row at the table end for a header only table, then close the table.
+#[inline]
+fn close_table(state: &mut RewriterState, end: &mut EndTag) {
+    if let Some((frame, padding)) = open_table(state) {
+        if frame.rows == 1 {
+            if let Some(delimiter) = frame.take_delimiter(padding) {
+                end.before(&delimiter, Text);
+            }
+        }
+    }
+
+    if let Some(frame) = state.tables.pop() {
+        frame.finish(&mut state.table_padding);
+    }
+}
+
+/// The line is a delimiter row: `|---|:-:|`.
+#[inline]
+fn is_delimiter_row(line: &str) -> bool {
+    line.strip_prefix('|')
+        .and_then(|line| line.strip_suffix('|'))
+        .is_some_and(|cells| {
+            cells.split('|').all(|cell| {
+                let dashes = cell.strip_prefix(':').unwrap_or(cell);
+                let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+                !dashes.is_empty() && dashes.bytes().all(|b| b == b'-')
+            })
+        })
+}
+
+/// Pad the header and delimiter rows of the tables with a row wider than their header,
+/// the delimited tables are found in document order.
+pub(crate) fn pad_table_headers(markdown: String, padding: &[usize]) -> String {
+    if padding.iter().all(|pad| *pad == 0) {
+        return markdown;
+    }
+
+    let mut lines: Vec<String> = markdown.split('\n').map(String::from).collect();
+    let mut padding = padding.iter();
+
+    for index in 1..lines.len() {
+        let row = lines[index].trim_start_matches([' ', '>']);
+        let header = lines[index - 1].trim_start_matches([' ', '>']);
+
+        if !is_delimiter_row(row) || !header.starts_with('|') {
+            continue;
+        }
+
+        let Some(&pad) = padding.next() else {
+            break;
+        };
+
+        for _ in 0..pad {
+            lines[index - 1].push('|');
+            lines[index].push_str("---|");
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Function to handle table elements
+///
+/// The first row of a table is the header row, a `|---|` delimiter row with
+/// one column per header cell is written after it. The column alignment comes
+/// from the header cells, then from the `<col>` and `<colgroup>` declarations.
+///
+/// The `colspan` and `rowspan` cells are expanded into a rectangular grid, the
+/// covered cells are blank or repeat the text of the spanning cell.
+#[inline]
+pub(crate) fn handle_table_element(
+    element: &mut Element,
+    state: &SharedState,
+    options: &ConversionOptions,
+    in_table: &mut bool,
+    table_row_start: &mut bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let element_name = element.tag_name();
+
+    match element_name.as_str() {
+        "table" => {
+            *in_table = true;
+            *table_row_start = false;
+
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                state
+                    .borrow_mut()
+                    .tables
+                    .push(TableFrame::new(options.span_policy));
+
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(move |end| {
+                    close_table(&mut state.borrow_mut(), end);
+                    Ok(())
+                }));
+            }
+        }
+        "tr" => {
+            if *in_table {
+                *table_row_start = true;
+            }
+
+            if let Some((frame, padding)) = open_table(&mut state.borrow_mut()) {
+                // the header row was not closed
+                if let Some(delimiter) = frame.start_row(padding) {
+                    element.before(&delimiter, Text);
+                    element.before("\n", Text);
+                }
+            }
+
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(move |end| {
+                    if let Some((frame, padding)) = open_table(&mut state.borrow_mut()) {
+                        end.before(&frame.end_row(padding), Text);
+                    }
+                    Ok(())
+                }));
+            }
+
+            insert_newline_after(element);
+        }
+        "colgroup" => {
+            let alignment = element_alignment(
+                element.get_attribute("align"),
+                element.get_attribute("style"),
+            );
+            let span = parse_span(element.get_attribute("span").as_deref());
+
+            if let Some(frame) = state.borrow_mut().tables.last_mut() {
+                frame.open_group(alignment, span);
+            }
+
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(move |_end| {
+                    if let Some(frame) = state.borrow_mut().tables.last_mut() {
+                        frame.finish_group();
+                    }
+                    Ok(())
+                }));
+            }
+        }
+        "col" => {
+            let alignment = element_alignment(
+                element.get_attribute("align"),
+                element.get_attribute("style"),
+            );
+            let span = parse_span(element.get_attribute("span").as_deref());
+
+            if let Some(frame) = state.borrow_mut().tables.last_mut() {
+                frame.push_col(alignment, span);
+            }
+        }
+        "th" | "td" => {
+            // a cell included without its table is written as its text
+            if state.borrow().tables.is_empty() {
+                return Ok(());
+            }
+
+            let colspan = parse_span(element.get_attribute("colspan").as_deref());
+            let rowspan = parse_span(element.get_attribute("rowspan").as_deref());
+            let alignment = element_alignment(
+                element.get_attribute("align"),
+                element.get_attribute("style"),
+            );
+
+            if *in_table && *table_row_start {
+                element.before("|", Html);
+                *table_row_start = false;
+            }
+
+            // the bold of a commonmark header cell closes before the covered cells
+            let bold = element_name == "th" && options.commonmark;
+            let closer = if bold { "**" } else { "" };
+            let mut closed = false;
+
+            if let Some(frame) = state.borrow_mut().tables.last_mut() {
+                let column = frame.column;
+                let covered = frame.start_cell(alignment, colspan, rowspan);
+
+                if !covered.is_empty() {
+                    element.before(&covered, Text);
+                }
+
+                if colspan > 1 || frame.cell_text.is_some() {
+                    if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                        closed = true;
+
+                        let state = state.clone();
+                        end_tag_handlers.push(Box::new(move |end| {
+                            if let Some(frame) = state.borrow_mut().tables.last_mut() {
+                                let covered = frame.end_cell(column, colspan, rowspan, closer);
+                                end.before(&covered, Text);
+                            }
+                            Ok(())
+                        }));
+                    }
+                }
+            }
+
+            if bold {
+                element.before("**", Html);
+            }
+            // a spanning cell closes its bold in the end tag handler
+            element.after(if bold && !closed { "**|" } else { "|" }, Html);
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Function to handle table elements (send)
+#[inline]
+pub(crate) fn handle_table_element_send(
+    element: &mut lol_html::send::Element,
+    state: &SharedStateSend,
+    options: &ConversionOptions,
+    in_table: &mut bool,
+    table_row_start: &mut bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let element_name = element.tag_name();
+
+    match element_name.as_str() {
+        "table" => {
+            *in_table = true;
+            *table_row_start = false;
+
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                lock_state(state)
+                    .tables
+                    .push(TableFrame::new(options.span_policy));
+
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+                    close_table(&mut lock_state(&state), end);
+                    Ok(())
+                }));
+            }
+        }
+        "tr" => {
+            if *in_table {
+                *table_row_start = true;
+            }
+
+            if let Some((frame, padding)) = open_table(&mut lock_state(state)) {
+                // the header row was not closed
+                if let Some(delimiter) = frame.start_row(padding) {
+                    element.before(&delimiter, Text);
+                    element.before("\n", Text);
+                }
+            }
+
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+                    if let Some((frame, padding)) = open_table(&mut lock_state(&state)) {
+                        end.before(&frame.end_row(padding), Text);
+                    }
+                    Ok(())
+                }));
+            }
+
+            insert_newline_after_send(element);
+        }
+        "colgroup" => {
+            let alignment = element_alignment(
+                element.get_attribute("align"),
+                element.get_attribute("style"),
+            );
+            let span = parse_span(element.get_attribute("span").as_deref());
+
+            if let Some(frame) = lock_state(state).tables.last_mut() {
+                frame.open_group(alignment, span);
+            }
+
+            if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                let state = state.clone();
+                end_tag_handlers.push(Box::new(move |_end: &mut EndTag<'_>| {
+                    if let Some(frame) = lock_state(&state).tables.last_mut() {
+                        frame.finish_group();
+                    }
+                    Ok(())
+                }));
+            }
+        }
+        "col" => {
+            let alignment = element_alignment(
+                element.get_attribute("align"),
+                element.get_attribute("style"),
+            );
+            let span = parse_span(element.get_attribute("span").as_deref());
+
+            if let Some(frame) = lock_state(state).tables.last_mut() {
+                frame.push_col(alignment, span);
+            }
+        }
+        "th" | "td" => {
+            // a cell included without its table is written as its text
+            if lock_state(state).tables.is_empty() {
+                return Ok(());
+            }
+
+            let colspan = parse_span(element.get_attribute("colspan").as_deref());
+            let rowspan = parse_span(element.get_attribute("rowspan").as_deref());
+            let alignment = element_alignment(
+                element.get_attribute("align"),
+                element.get_attribute("style"),
+            );
+
+            if *in_table && *table_row_start {
+                element.before("|", Html);
+                *table_row_start = false;
+            }
+
+            // the bold of a commonmark header cell closes before the covered cells
+            let bold = element_name == "th" && options.commonmark;
+            let closer = if bold { "**" } else { "" };
+            let mut closed = false;
+
+            if let Some(frame) = lock_state(state).tables.last_mut() {
+                let column = frame.column;
+                let covered = frame.start_cell(alignment, colspan, rowspan);
+
+                if !covered.is_empty() {
+                    element.before(&covered, Text);
+                }
+
+                if colspan > 1 || frame.cell_text.is_some() {
+                    if let Some(end_tag_handlers) = element.end_tag_handlers() {
+                        closed = true;
+
+                        let state = state.clone();
+                        end_tag_handlers.push(Box::new(move |end: &mut EndTag<'_>| {
+                            if let Some(frame) = lock_state(&state).tables.last_mut() {
+                                let covered = frame.end_cell(column, colspan, rowspan, closer);
+                                end.before(&covered, Text);
+                            }
+                            Ok(())
+                        }));
+                    }
+                }
+            }
+
+            if bold {
+                element.before("**", Html);
+            }
+            // a spanning cell closes its bold in the end tag handler
+            element.after(if bold && !closed { "**|" } else { "|" }, Html);
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
@@ -1,15 +1,34 @@
 use super::handle::handle_tag;
 use super::quotes::rewrite_blockquote_text;
-use crate::clean_markdown_bytes;
+use crate::rewriter::anchors::link_definitions;
+use crate::rewriter::codes::{capture_code_text, take_code_block};
+use crate::rewriter::custom::handle_custom_element;
+use crate::rewriter::definitions::{trim_term_text, write_term_space};
+use crate::rewriter::extract::{capture_link_text, is_extracting, ConversionOutput, Extraction};
+use crate::rewriter::footnotes::{capture_citation_text, missing_note_definitions};
+use crate::rewriter::headings::{capture_heading_element, capture_heading_text};
+use crate::rewriter::lines::{escape_line_starts, line_start_at};
+use crate::rewriter::math::{capture_math_script_text, capture_math_text};
+use crate::rewriter::metadata::{
+    capture_title_text, front_matter, is_capturing_metadata, Metadata,
+};
+use crate::rewriter::references::join_references;
+use crate::rewriter::state::{lock_state, RewriterState, SharedState, SharedStateSend};
+use crate::rewriter::tables::{capture_cell_text, pad_table_headers};
 use crate::rewriter::{handle::handle_tag_send, quotes::rewrite_blockquote_text_send};
-use lol_html::{doc_comments, doctype, element, html_content::EndTag, text, RewriteStrSettings};
-use std::cell::Cell;
+use crate::ConversionOptions;
+use crate::{clean_markdown_bytes, wrap_markdown_opt};
+use lol_html::{
+    doc_comments, doctype, element, end,
+    html_content::{ContentType, EndTag},
+    text, RewriteStrSettings,
+};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::sync::{
-    atomic::{AtomicU8, AtomicUsize, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+    Arc, Mutex,
 };
-use url::Url;
 
 lazy_static::lazy_static! {
     #[cfg(feature = "ignore_cookies")]
@@ -55,9 +74,31 @@ fn estimate_markdown(html: &str) -> usize {
     }
 }
 
+/// The selector list of the valid selectors, the invalid ones are dropped since lol_html
+/// panics on them. None when no selector is left.
+fn selector_list<'a>(selectors: impl IntoIterator<Item = &'a String>) -> Option<String> {
+    let list = selectors
+        .into_iter()
+        .filter(|selector| selector.parse::<lol_html::Selector>().is_ok())
+        .cloned()
+        .collect::<Vec<String>>()
+        .join(",");
+
+    (!list.is_empty()).then_some(list)
+}
+
+/// The `<head>` elements read outside the included elements.
+#[inline]
+fn is_head_metadata(tag_name: &str) -> bool {
+    matches!(tag_name, "html" | "base" | "title" | "meta" | "link")
+}
+
 // ===== send flags packed into one atomic =====
 const F_IN_TABLE: u8 = 1 << 0;
 const F_LI_START: u8 = 1 << 1;
+const F_IN_CODE: u8 = 1 << 2;
+const F_IN_CODE_SPAN: u8 = 1 << 3;
+const F_LINE_START: u8 = 1 << 4;
 
 #[inline]
 fn flag_set(flags: &AtomicU8, mask: u8) {
@@ -69,14 +110,31 @@ fn flag_clear(flags: &AtomicU8, mask: u8) {
     let _ = flags.fetch_and(!mask, Ordering::Relaxed);
 }
 
+#[inline]
+fn set_line_start(flags: &AtomicU8, line_start: bool) {
+    if line_start {
+        flag_set(flags, F_LINE_START);
+    } else {
+        flag_clear(flags, F_LINE_START);
+    }
+}
+
 /// Get the HTML rewriter settings to convert to markdown.
-pub fn get_rewriter_settings(
-    commonmark: bool,
-    custom: &Option<std::collections::HashSet<String>>,
-    url: Option<Url>,
+///
+/// The definitions of reference links, the front matter and the wrapping are added by the
+/// conversion functions. Driving lol_html with these settings and `LinkStyle::Referenced`
+/// writes the `[text][1]` markers without their definitions, use the `*_with_options`
+/// entry points for reference links.
+pub fn get_rewriter_settings(options: &ConversionOptions) -> RewriteStrSettings<'static, 'static> {
+    rewriter_settings(options, Rc::new(RefCell::new(RewriterState::default())))
+}
+
+/// Get the HTML rewriter settings to convert to markdown sharing the document state.
+pub(crate) fn rewriter_settings(
+    options: &ConversionOptions,
+    state: SharedState,
 ) -> RewriteStrSettings<'static, 'static> {
-    let mut list_type: Option<&'static str> = None;
-    let mut order_counter = 0usize;
+    let options = Arc::new(options.clone());
 
     let quote_depth = Rc::new(AtomicUsize::new(0));
     let quote_depth1 = quote_depth.clone();
@@ -86,16 +144,17 @@ pub fn get_rewriter_settings(
     // flags (non-send) are already fast
     let list_item_start_flag = Rc::new(Cell::new(false));
     let in_table_flag = Rc::new(Cell::new(false));
+    let in_code_flag = Rc::new(Cell::new(false));
+    let in_code_span_flag = Rc::new(Cell::new(false));
+    let line_start_flag = Rc::new(Cell::new(true));
 
     // state passed into handle_tag
-    let mut in_table = false;
     let mut table_row_start = false;
-    let mut list_item_start = false;
 
     let mut element_content_handlers = Vec::with_capacity(
-        4 + custom
-            .as_ref()
-            .map_or(0, |c| if c.is_empty() { 0 } else { 1 })
+        5 + usize::from(!options.ignore.is_empty())
+            + usize::from(!options.include.is_empty())
+            + options.custom_handlers.len()
             + {
                 #[cfg(feature = "ignore_cookies")]
                 {
@@ -116,19 +175,78 @@ pub fn get_rewriter_settings(
         }));
     }
 
-    element_content_handlers.push(text!("blockquote, q, cite", move |el| {
-        let _ = rewrite_blockquote_text(el, &quote_depth1);
+    // the TeX of the MathJax scripts, written at their end tag
+    let state_script = state.clone();
+    element_content_handlers.push(text!("script[type^='math/tex']", move |el| {
+        capture_math_script_text(&mut state_script.borrow_mut(), el.as_str());
+        *el.as_mut_str() = String::new();
         Ok(())
     }));
 
     // TEXT HANDLER: drop whitespace-only nodes inside tables + at list item start
     let list_item_start_flag_text = list_item_start_flag.clone();
     let in_table_flag_text = in_table_flag.clone();
+    let in_code_flag_text = in_code_flag.clone();
+    let in_code_span_flag_text = in_code_span_flag.clone();
+    let line_start_flag_text = line_start_flag.clone();
+    let include_selector = selector_list(&options.include);
+    let including = include_selector.is_some();
+    let included_depth = Rc::new(Cell::new(0usize));
+    let included_depth_text = included_depth.clone();
+    let included_void = Rc::new(Cell::new(false));
+    if options.front_matter {
+        state.borrow_mut().metadata = Some(Metadata::default());
+    }
+    state.borrow_mut().code_fence = options.style.code_fence;
+
+    let state_text = state.clone();
+    let extracting = is_extracting(&state.borrow());
+    let capturing_metadata = is_capturing_metadata(&state.borrow());
     element_content_handlers.push(text!(
         "*:not(script):not(head):not(style):not(svg)",
         move |el| {
             let s = el.as_str();
 
+            // the <title> of the front matter is not part of the body
+            if capturing_metadata && capture_title_text(&mut state_text.borrow_mut(), s) {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // outside the included elements
+            if including && included_depth_text.get() == 0 {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // the TeX is written at the end tag of the math
+            if capture_math_text(&mut state_text.borrow_mut(), s) {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // the text of a citation is the label of its footnote
+            if capture_citation_text(&mut state_text.borrow_mut(), s) {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // a character reference split by the chunks is decoded whole
+            let joined = join_references(
+                s,
+                &mut state_text.borrow_mut().reference,
+                el.last_in_text_node(),
+            );
+            let s = joined.as_deref().unwrap_or(s);
+
+            // code blocks are buffered verbatim and written at the end tag
+            if in_code_flag_text.get() {
+                let text = crate::decode_text_opt(s);
+                capture_code_text(&mut state_text.borrow_mut(), text.as_deref().unwrap_or(s));
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
             // inside table: ignore formatting whitespace between cells
             if in_table_flag_text.get() && is_ascii_ws_only(s) {
                 *el.as_mut_str() = String::new();
@@ -144,16 +262,63 @@ pub fn get_rewriter_settings(
                 list_item_start_flag_text.set(false);
             }
 
+            // the text of the anchors is collected alongside the markdown
+            if extracting {
+                let text = crate::decode_text_opt(s);
+                capture_link_text(&mut state_text.borrow_mut(), text.as_deref().unwrap_or(s));
+            }
+
+            // inline code is buffered verbatim and written at the end tag
+            if in_code_span_flag_text.get() {
+                let text = crate::decode_text_opt(s);
+                capture_code_text(&mut state_text.borrow_mut(), text.as_deref().unwrap_or(s));
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // the strong term holds back its outer whitespace
+            let term_text = trim_term_text(&mut state_text.borrow_mut(), s);
+            let s = term_text.as_deref().unwrap_or(s);
+
             // Only allocate if escaping is actually needed
-            if let Some(escaped) = crate::replace_markdown_chars_opt(s) {
+            let mut line_start = line_start_flag_text.get();
+            let escaped = crate::replace_markdown_chars_opt(s);
+            let escaped = escape_line_starts(
+                escaped.as_deref().unwrap_or(s),
+                &mut line_start,
+                &mut state_text.borrow_mut().line_start,
+                el.last_in_text_node(),
+            )
+            .or(escaped);
+            line_start_flag_text.set(line_start);
+
+            if let Some(escaped) = escaped.or(term_text).or(joined) {
                 *el.as_mut_str() = escaped;
             }
+
+            // the underline of a setext heading is written under its text
+            capture_heading_text(&mut state_text.borrow_mut(), el.as_str());
+
+            // spanning cells repeat their text
+            if in_table_flag_text.get() {
+                capture_cell_text(&mut state_text.borrow_mut(), el.as_str());
+            }
             Ok(())
         }
     ));
 
+    // the quoted text is escaped first, the text of the code is buffered and quoted at its end tag
+    let in_code_flag_quote = in_code_flag.clone();
+    let in_code_span_flag_quote = in_code_span_flag.clone();
+    element_content_handlers.push(text!("blockquote, q, cite", move |el| {
+        if !in_code_flag_quote.get() && !in_code_span_flag_quote.get() {
+            let _ = rewrite_blockquote_text(el, &quote_depth1);
+        }
+        Ok(())
+    }));
+
     element_content_handlers.push(element!(
-        "head, nav, footer, script, noscript, style",
+        "head, nav, footer, script:not([type^='math/tex']), noscript, style",
         move |el| {
             let repaired_head_element: bool = repaired_head.get().is_some();
             let head_element = el.tag_name() == "head";
@@ -180,11 +345,72 @@ pub fn get_rewriter_settings(
         }
     ));
 
+    // INCLUDE HANDLER: count the open included elements
+    if let Some(include_selector) = include_selector {
+        let included_depth = included_depth.clone();
+        let included_void = included_void.clone();
+        element_content_handlers.push(element!(include_selector, move |el| {
+            if let Some(hvec) = el.end_tag_handlers() {
+                included_depth.set(included_depth.get() + 1);
+
+                let included_depth_end = included_depth.clone();
+                let h: LocalEndHandler =
+                    Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                        included_depth_end.set(included_depth_end.get() - 1);
+                        Ok(())
+                    });
+                hvec.push(h);
+            } else {
+                // a void element is included for its own start tag
+                included_void.set(true);
+            }
+            Ok(())
+        }));
+    }
+
+    // CUSTOM HANDLERS: registered by the user, run before the built-in conversion
+    for (selector, handler) in options.custom_handlers.iter() {
+        let Some(selector) = selector_list([selector]) else {
+            continue;
+        };
+        let handler = handler.clone();
+        let included_depth = included_depth.clone();
+        let included_void = included_void.clone();
+        element_content_handlers.push(element!(selector, move |el| {
+            if !including || included_depth.get() > 0 || included_void.get() {
+                handle_custom_element(el, handler.as_ref());
+            }
+            Ok(())
+        }));
+    }
+
     // ELEMENT HANDLER: manage flags + call handle_tag
     let list_item_start_flag_el = list_item_start_flag.clone();
     let in_table_flag_el = in_table_flag.clone();
+    let in_code_flag_el = in_code_flag.clone();
+    let in_code_span_flag_el = in_code_span_flag.clone();
+    let line_start_flag_el = line_start_flag.clone();
+    let options_el = options.clone();
+    let state_end = state.clone();
 
     element_content_handlers.push(element!("*", move |el| {
+        let included_void = included_void.replace(false);
+
+        // replaced by a custom handler
+        if el.removed() {
+            return Ok(());
+        }
+
+        // outside the included elements only the <head> metadata is read
+        if including
+            && included_depth.get() == 0
+            && !included_void
+            && !is_head_metadata(&el.tag_name())
+        {
+            el.remove_and_keep_content();
+            return Ok(());
+        }
+
         // Table start: enable flag and add end-tag handler to disable.
         if el.tag_name().as_str() == "table" {
             in_table_flag_el.set(true);
@@ -199,16 +425,69 @@ pub fn get_rewriter_settings(
             }
         }
 
+        // Code block start: the text is buffered until the end tag.
+        if el.tag_name().as_str() == "pre" && !in_code_flag_el.get() {
+            if let Some(hvec) = el.end_tag_handlers() {
+                in_code_flag_el.set(true);
+
+                let in_code_flag_end = in_code_flag_el.clone();
+                let h: LocalEndHandler =
+                    Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                        in_code_flag_end.set(false);
+                        Ok(())
+                    });
+                hvec.push(h);
+            }
+        }
+
+        // Inline code start: the text is buffered until the end tag.
+        if matches!(el.tag_name().as_str(), "code" | "samp")
+            && !in_code_flag_el.get()
+            && !in_code_span_flag_el.get()
+        {
+            if let Some(hvec) = el.end_tag_handlers() {
+                in_code_span_flag_el.set(true);
+
+                let in_code_span_flag_end = in_code_span_flag_el.clone();
+                let h: LocalEndHandler =
+                    Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                        in_code_span_flag_end.set(false);
+                        Ok(())
+                    });
+                hvec.push(h);
+            }
+        }
+
+        // Line start: the text after a block begins a line, inline markup continues it.
+        if let Some(line_start) = line_start_at(&el.tag_name()) {
+            line_start_flag_el.set(line_start);
+
+            if line_start {
+                if let Some(hvec) = el.end_tag_handlers() {
+                    let line_start_flag_end = line_start_flag_el.clone();
+                    let h: LocalEndHandler =
+                        Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                            line_start_flag_end.set(true);
+                            Ok(())
+                        });
+                    hvec.push(h);
+                }
+            }
+        }
+
+        // the whitespace held back by a strong term goes before the markup of the element
+        write_term_space(&mut state.borrow_mut(), el);
+        // the inline markup of a setext heading is underlined
+        capture_heading_element(&mut state.borrow_mut(), &el.tag_name());
+
         // sync state from flags
-        in_table = in_table_flag_el.get();
-        list_item_start = list_item_start_flag_el.get();
+        let mut in_table = in_table_flag_el.get();
+        let mut list_item_start = list_item_start_flag_el.get();
 
         let _ = handle_tag(
             el,
-            commonmark,
-            &url,
-            &mut list_type,
-            &mut order_counter,
+            &options_el,
+            &state,
             quote_depth.clone(),
             &mut in_table,
             &mut table_row_start,
@@ -221,17 +500,20 @@ pub fn get_rewriter_settings(
         Ok(())
     }));
 
-    if let Some(ignore) = custom {
-        if !ignore.is_empty() {
-            let ignore_handler = element!(
-                ignore.iter().cloned().collect::<Vec<String>>().join(","),
-                |el| {
-                    el.remove();
-                    Ok(())
-                }
-            );
-            element_content_handlers.push(ignore_handler);
-        }
+    if !options.ignore.is_empty() {
+        let ignore_handler = element!(
+            options
+                .ignore
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(","),
+            |el| {
+                el.remove();
+                Ok(())
+            }
+        );
+        element_content_handlers.push(ignore_handler);
     }
 
     RewriteStrSettings {
@@ -244,6 +526,13 @@ pub fn get_rewriter_settings(
                 c.remove();
                 Ok(())
             }),
+            end!(move |end| {
+                // a <pre> left open by truncated html
+                if let Some(block) = take_code_block(&mut state_end.borrow_mut()) {
+                    end.append(&block, ContentType::Html);
+                }
+                Ok(())
+            }),
         ],
         element_content_handlers,
         ..RewriteStrSettings::default()
@@ -251,13 +540,23 @@ pub fn get_rewriter_settings(
 }
 
 /// Get the HTML rewriter settings to convert to markdown sync send.
+///
+/// The definitions of reference links, the front matter and the wrapping are added by the
+/// conversion functions. Driving lol_html with these settings and `LinkStyle::Referenced`
+/// writes the `[text][1]` markers without their definitions, use the `*_with_options`
+/// entry points for reference links.
 pub fn get_rewriter_settings_send(
-    commonmark: bool,
-    custom: &Option<std::collections::HashSet<String>>,
-    url: Option<Url>,
+    options: &ConversionOptions,
 ) -> lol_html::send::Settings<'static, 'static> {
-    let mut list_type: Option<&'static str> = None;
-    let mut order_counter = 0usize;
+    rewriter_settings_send(options, Arc::new(Mutex::new(RewriterState::default())))
+}
+
+/// Get the HTML rewriter settings to convert to markdown sync send sharing the document state.
+pub(crate) fn rewriter_settings_send(
+    options: &ConversionOptions,
+    state: SharedStateSend,
+) -> lol_html::send::Settings<'static, 'static> {
+    let options = Arc::new(options.clone());
 
     let quote_depth = Arc::new(AtomicUsize::new(0));
     let quote_depth1 = quote_depth.clone();
@@ -265,17 +564,15 @@ pub fn get_rewriter_settings_send(
     let repaired_head = Arc::new(std::sync::OnceLock::new());
 
     // packed flags (single atomic load per handler call)
-    let flags = Arc::new(AtomicU8::new(0));
+    let flags = Arc::new(AtomicU8::new(F_LINE_START));
 
     // state passed into handle_tag_send
-    let mut in_table = false;
     let mut table_row_start = false;
-    let mut list_item_start = false;
 
     let mut element_content_handlers = Vec::with_capacity(
-        4 + custom
-            .as_ref()
-            .map_or(0, |c| if c.is_empty() { 0 } else { 1 })
+        5 + usize::from(!options.ignore.is_empty())
+            + usize::from(!options.include.is_empty())
+            + options.custom_handlers.len()
             + {
                 #[cfg(feature = "ignore_cookies")]
                 {
@@ -296,13 +593,28 @@ pub fn get_rewriter_settings_send(
         }));
     }
 
-    element_content_handlers.push(text!("blockquote, q, cite", move |el| {
-        let _ = rewrite_blockquote_text_send(el, &quote_depth);
+    let state_script = state.clone();
+    element_content_handlers.push(text!("script[type^='math/tex']", move |el| {
+        capture_math_script_text(&mut lock_state(&state_script), el.as_str());
+        *el.as_mut_str() = String::new();
         Ok(())
     }));
 
     // TEXT HANDLER (send): single atomic load + ASCII whitespace scan
     let flags_text = flags.clone();
+    let include_selector = selector_list(&options.include);
+    let including = include_selector.is_some();
+    let included_depth = Arc::new(AtomicUsize::new(0));
+    let included_depth_text = included_depth.clone();
+    let included_void = Arc::new(AtomicBool::new(false));
+    if options.front_matter {
+        lock_state(&state).metadata = Some(Metadata::default());
+    }
+    lock_state(&state).code_fence = options.style.code_fence;
+
+    let state_text = state.clone();
+    let extracting = is_extracting(&lock_state(&state));
+    let capturing_metadata = is_capturing_metadata(&lock_state(&state));
     element_content_handlers.push(text!(
         "*:not(script):not(head):not(style):not(svg)",
         move |el| {
@@ -312,6 +624,46 @@ pub fn get_rewriter_settings_send(
 
             let s = el.as_str();
 
+            // the <title> of the front matter is not part of the body
+            if capturing_metadata && capture_title_text(&mut lock_state(&state_text), s) {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // outside the included elements
+            if including && included_depth_text.load(Ordering::Relaxed) == 0 {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // the TeX is written at the end tag of the math
+            if capture_math_text(&mut lock_state(&state_text), s) {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // the text of a citation is the label of its footnote
+            if capture_citation_text(&mut lock_state(&state_text), s) {
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
+            // a character reference split by the chunks is decoded whole
+            let joined = join_references(
+                s,
+                &mut lock_state(&state_text).reference,
+                el.last_in_text_node(),
+            );
+            let s = joined.as_deref().unwrap_or(s);
+
+            // code blocks are buffered verbatim and written at the end tag
+            if (f & F_IN_CODE) != 0 {
+                let text = crate::decode_text_opt(s);
+                capture_code_text(&mut lock_state(&state_text), text.as_deref().unwrap_or(s));
+                *el.as_mut_str() = String::new();
+                return Ok(());
+            }
+
             if in_table_now && is_ascii_ws_only(s) {
                 *el.as_mut_str() = String::new();
                 return Ok(());
@@ -323,19 +675,65 @@ pub fn get_rewriter_settings_send(
                     return Ok(());
                 }
                 // clear li-start
-                flag_clear(&*flags_text, F_LI_START);
+                flag_clear(&flags_text, F_LI_START);
+            }
+
+            // the text of the anchors is collected alongside the markdown
+            if extracting {
+                let text = crate::decode_text_opt(s);
+                capture_link_text(&mut lock_state(&state_text), text.as_deref().unwrap_or(s));
+            }
+
+            // inline code is buffered verbatim and written at the end tag
+            if (f & F_IN_CODE_SPAN) != 0 {
+                let text = crate::decode_text_opt(s);
+                capture_code_text(&mut lock_state(&state_text), text.as_deref().unwrap_or(s));
+                *el.as_mut_str() = String::new();
+                return Ok(());
             }
 
+            // the strong term holds back its outer whitespace
+            let term_text = trim_term_text(&mut lock_state(&state_text), s);
+            let s = term_text.as_deref().unwrap_or(s);
+
             // Only allocate if escaping is actually needed
-            if let Some(escaped) = crate::replace_markdown_chars_opt(s) {
+            let mut line_start = (f & F_LINE_START) != 0;
+            let escaped = crate::replace_markdown_chars_opt(s);
+            let escaped = escape_line_starts(
+                escaped.as_deref().unwrap_or(s),
+                &mut line_start,
+                &mut lock_state(&state_text).line_start,
+                el.last_in_text_node(),
+            )
+            .or(escaped);
+            set_line_start(&flags_text, line_start);
+
+            if let Some(escaped) = escaped.or(term_text).or(joined) {
                 *el.as_mut_str() = escaped;
             }
+
+            // the underline of a setext heading is written under its text
+            capture_heading_text(&mut lock_state(&state_text), el.as_str());
+
+            // spanning cells repeat their text
+            if in_table_now {
+                capture_cell_text(&mut lock_state(&state_text), el.as_str());
+            }
             Ok(())
         }
     ));
 
+    // the quoted text is escaped first, the text of the code is buffered and quoted at its end tag
+    let flags_quote = flags.clone();
+    element_content_handlers.push(text!("blockquote, q, cite", move |el| {
+        if flags_quote.load(Ordering::Relaxed) & (F_IN_CODE | F_IN_CODE_SPAN) == 0 {
+            let _ = rewrite_blockquote_text_send(el, &quote_depth);
+        }
+        Ok(())
+    }));
+
     element_content_handlers.push(element!(
-        "head, nav, footer, script, noscript, style",
+        "head, nav, footer, script:not([type^='math/tex']), noscript, style",
         move |el| {
             let repaired_head_element: bool = repaired_head.get().is_some();
             let head_element = el.tag_name() == "head";
@@ -362,35 +760,148 @@ pub fn get_rewriter_settings_send(
         }
     ));
 
+    // INCLUDE HANDLER (send): count the open included elements
+    if let Some(include_selector) = include_selector {
+        let included_depth = included_depth.clone();
+        let included_void = included_void.clone();
+        element_content_handlers.push(element!(include_selector, move |el| {
+            if let Some(hvec) = el.end_tag_handlers() {
+                included_depth.fetch_add(1, Ordering::Relaxed);
+
+                let included_depth_end = included_depth.clone();
+                let h: EndHandler =
+                    Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                        included_depth_end.fetch_sub(1, Ordering::Relaxed);
+                        Ok(())
+                    });
+                hvec.push(h);
+            } else {
+                // a void element is included for its own start tag
+                included_void.store(true, Ordering::Relaxed);
+            }
+            Ok(())
+        }));
+    }
+
+    // CUSTOM HANDLERS (send)
+    for (selector, handler) in options.custom_handlers.iter() {
+        let Some(selector) = selector_list([selector]) else {
+            continue;
+        };
+        let handler = handler.clone();
+        let included_depth = included_depth.clone();
+        let included_void = included_void.clone();
+        element_content_handlers.push(element!(selector, move |el| {
+            if !including
+                || included_depth.load(Ordering::Relaxed) > 0
+                || included_void.load(Ordering::Relaxed)
+            {
+                handle_custom_element(el, handler.as_ref());
+            }
+            Ok(())
+        }));
+    }
+
     // ELEMENT HANDLER (send): set/clear packed flags + call handle_tag_send
     let flags_el = flags.clone();
+    let options_el = options.clone();
+    let state_end = state.clone();
     element_content_handlers.push(element!("*", move |el| {
+        let included_void = included_void.swap(false, Ordering::Relaxed);
+
+        // replaced by a custom handler
+        if el.removed() {
+            return Ok(());
+        }
+
+        // outside the included elements only the <head> metadata is read
+        if including
+            && included_depth.load(Ordering::Relaxed) == 0
+            && !included_void
+            && !is_head_metadata(&el.tag_name())
+        {
+            el.remove_and_keep_content();
+            return Ok(());
+        }
+
         // table start
         if el.tag_name().as_str() == "table" {
-            flag_set(&*flags_el, F_IN_TABLE);
+            flag_set(&flags_el, F_IN_TABLE);
 
             if let Some(hvec) = el.end_tag_handlers() {
                 let flags_end = flags_el.clone();
                 let h: EndHandler =
                     Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
-                        flag_clear(&*flags_end, F_IN_TABLE);
+                        flag_clear(&flags_end, F_IN_TABLE);
                         Ok(())
                     });
                 hvec.push(h);
             }
         }
 
+        // code block start
+        if el.tag_name().as_str() == "pre" && (flags_el.load(Ordering::Relaxed) & F_IN_CODE) == 0 {
+            if let Some(hvec) = el.end_tag_handlers() {
+                flag_set(&flags_el, F_IN_CODE);
+
+                let flags_end = flags_el.clone();
+                let h: EndHandler =
+                    Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                        flag_clear(&flags_end, F_IN_CODE);
+                        Ok(())
+                    });
+                hvec.push(h);
+            }
+        }
+
+        // inline code start
+        if matches!(el.tag_name().as_str(), "code" | "samp")
+            && (flags_el.load(Ordering::Relaxed) & (F_IN_CODE | F_IN_CODE_SPAN)) == 0
+        {
+            if let Some(hvec) = el.end_tag_handlers() {
+                flag_set(&flags_el, F_IN_CODE_SPAN);
+
+                let flags_end = flags_el.clone();
+                let h: EndHandler =
+                    Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                        flag_clear(&flags_end, F_IN_CODE_SPAN);
+                        Ok(())
+                    });
+                hvec.push(h);
+            }
+        }
+
+        // line start
+        if let Some(line_start) = line_start_at(&el.tag_name()) {
+            set_line_start(&flags_el, line_start);
+
+            if line_start {
+                if let Some(hvec) = el.end_tag_handlers() {
+                    let flags_end = flags_el.clone();
+                    let h: EndHandler =
+                        Box::new(move |_end: &mut lol_html::html_content::EndTag<'_>| {
+                            flag_set(&flags_end, F_LINE_START);
+                            Ok(())
+                        });
+                    hvec.push(h);
+                }
+            }
+        }
+
+        // the whitespace held back by a strong term goes before the markup of the element
+        write_term_space(&mut lock_state(&state), el);
+        // the inline markup of a setext heading is underlined
+        capture_heading_element(&mut lock_state(&state), &el.tag_name());
+
         // local bools for handle_tag_send
         let f = flags_el.load(Ordering::Relaxed);
-        in_table = (f & F_IN_TABLE) != 0;
-        list_item_start = (f & F_LI_START) != 0;
+        let mut in_table = (f & F_IN_TABLE) != 0;
+        let mut list_item_start = (f & F_LI_START) != 0;
 
         let _ = handle_tag_send(
             el,
-            commonmark,
-            &url,
-            &mut list_type,
-            &mut order_counter,
+            &options_el,
+            &state,
             quote_depth1.clone(),
             &mut in_table,
             &mut table_row_start,
@@ -399,25 +910,28 @@ pub fn get_rewriter_settings_send(
 
         // mirror li-start back into packed flags
         if list_item_start {
-            flag_set(&*flags_el, F_LI_START);
+            flag_set(&flags_el, F_LI_START);
         } else {
-            flag_clear(&*flags_el, F_LI_START);
+            flag_clear(&flags_el, F_LI_START);
         }
 
         Ok(())
     }));
 
-    if let Some(ignore) = custom {
-        if !ignore.is_empty() {
-            let ignore_handler = element!(
-                ignore.iter().cloned().collect::<Vec<String>>().join(","),
-                |el| {
-                    el.remove();
-                    Ok(())
-                }
-            );
-            element_content_handlers.push(ignore_handler);
-        }
+    if !options.ignore.is_empty() {
+        let ignore_handler = element!(
+            options
+                .ignore
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(","),
+            |el| {
+                el.remove();
+                Ok(())
+            }
+        );
+        element_content_handlers.push(ignore_handler);
     }
 
     lol_html::send::Settings {
@@ -430,6 +944,12 @@ pub fn get_rewriter_settings_send(
                 c.remove();
                 Ok(())
             }),
+            end!(move |end| {
+                if let Some(block) = take_code_block(&mut lock_state(&state_end)) {
+                    end.append(&block, ContentType::Html);
+                }
+                Ok(())
+            }),
         ],
         element_content_handlers,
         ..lol_html::send::Settings::new_send()
@@ -456,31 +976,126 @@ pub(crate) fn rewrite_str<'h, 's, H: lol_html::HandlerTypes>(
 /// Convert to markdown streaming re-writer
 pub(crate) fn convert_html_to_markdown(
     html: &str,
-    custom: &Option<std::collections::HashSet<String>>,
-    commonmark: bool,
-    url: &Option<Url>,
+    options: &ConversionOptions,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let settings = get_rewriter_settings(commonmark, custom, url.clone());
+    convert_html_with_state(
+        html,
+        options,
+        Rc::new(RefCell::new(RewriterState::default())),
+    )
+}
+
+/// Convert to markdown streaming re-writer collecting the links and images.
+pub(crate) fn convert_html_to_markdown_with_links(
+    html: &str,
+    options: &ConversionOptions,
+) -> Result<ConversionOutput, Box<dyn std::error::Error>> {
+    let state: SharedState = Rc::new(RefCell::new(RewriterState {
+        extraction: Some(Extraction::default()),
+        ..Default::default()
+    }));
+    let markdown = convert_html_with_state(html, options, state.clone())?;
+    let extraction = state.borrow_mut().extraction.take().unwrap_or_default();
+
+    Ok(extraction.into_output(markdown))
+}
+
+/// Convert to markdown streaming re-writer sharing the document state.
+fn convert_html_with_state(
+    html: &str,
+    options: &ConversionOptions,
+    state: SharedState,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let settings = rewriter_settings(options, state.clone());
 
     match rewrite_str(html, settings) {
-        Ok(markdown) => Ok(clean_markdown_bytes(&markdown)),
+        Ok(markdown) => {
+            let markdown = clean_markdown_bytes(&markdown);
+            let markdown = pad_table_headers(markdown, &state.borrow().table_padding);
+            let mut markdown = wrap_markdown_opt(markdown, options);
+            // after the cleanup, the link definitions need the blank line before them
+            markdown.push_str(&missing_note_definitions(&state.borrow()));
+            markdown.push_str(&link_definitions(&state.borrow()));
+            markdown.insert_str(0, &front_matter(&state.borrow()));
+            Ok(markdown)
+        }
         Err(e) => Err(e.into()),
     }
 }
 
 /// Convert to markdown streaming re-writer with chunk size.
 #[cfg(feature = "stream")]
+#[deprecated(note = "use `convert_html_to_markdown_send_with_options` with the chunk size option")]
 pub async fn convert_html_to_markdown_send_with_size(
     html: &str,
     custom: &Option<std::collections::HashSet<String>>,
     commonmark: bool,
-    url: &Option<Url>,
+    url: &Option<url::Url>,
     chunk_size: usize,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let settings = get_rewriter_settings_send(commonmark, custom, url.clone());
+    let options = ConversionOptions::default()
+        .with_commonmark(commonmark)
+        .with_ignore(custom.clone().unwrap_or_default())
+        .with_url(url.clone())
+        .with_chunk_size(chunk_size);
+
+    convert_html_to_markdown_send_with_options(html, &options).await
+}
+
+/// Convert to markdown streaming re-writer
+#[cfg(feature = "stream")]
+#[deprecated(note = "use `convert_html_to_markdown_send_with_options`")]
+pub async fn convert_html_to_markdown_send(
+    html: &str,
+    custom: &Option<std::collections::HashSet<String>>,
+    commonmark: bool,
+    url: &Option<url::Url>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    #[allow(deprecated)]
+    convert_html_to_markdown_send_with_size(html, custom, commonmark, url, 8192).await
+}
+
+/// Convert to markdown streaming re-writer writing the html in chunks of `options.chunk_size`.
+#[cfg(feature = "stream")]
+pub async fn convert_html_to_markdown_send_with_options(
+    html: &str,
+    options: &ConversionOptions,
+) -> Result<String, Box<dyn std::error::Error>> {
+    convert_html_with_state_send(
+        html,
+        options,
+        Arc::new(Mutex::new(RewriterState::default())),
+    )
+    .await
+}
+
+/// Convert to markdown streaming re-writer writing the html in chunks collecting the links and images.
+#[cfg(feature = "stream")]
+pub async fn convert_html_to_markdown_with_links_send(
+    html: &str,
+    options: &ConversionOptions,
+) -> Result<ConversionOutput, Box<dyn std::error::Error>> {
+    let state: SharedStateSend = Arc::new(Mutex::new(RewriterState {
+        extraction: Some(Extraction::default()),
+        ..Default::default()
+    }));
+    let markdown = convert_html_with_state_send(html, options, state.clone()).await?;
+    let extraction = lock_state(&state).extraction.take().unwrap_or_default();
+
+    Ok(extraction.into_output(markdown))
+}
+
+/// Convert to markdown streaming re-writer writing the html in chunks sharing the document state.
+#[cfg(feature = "stream")]
+async fn convert_html_with_state_send(
+    html: &str,
+    options: &ConversionOptions,
+    state: SharedStateSend,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let settings = rewriter_settings_send(options, state.clone());
     let mut rewrited_bytes: Vec<u8> = Vec::with_capacity(estimate_markdown(html));
 
-    let mut rewriter = lol_html::send::HtmlRewriter::new(settings.into(), |c: &[u8]| {
+    let mut rewriter = lol_html::send::HtmlRewriter::new(settings, |c: &[u8]| {
         rewrited_bytes.extend_from_slice(c);
     });
 
@@ -488,7 +1103,7 @@ pub async fn convert_html_to_markdown_send_with_size(
 
     // Process in chunks without async overhead for in-memory data
     let mut wrote_error = false;
-    for chunk in bytes.chunks(chunk_size) {
+    for chunk in bytes.chunks(options.chunk_size.max(1)) {
         if rewriter.write(chunk).is_err() {
             wrote_error = true;
             break;
@@ -499,18 +1114,14 @@ pub async fn convert_html_to_markdown_send_with_size(
         let _ = rewriter.end();
     }
 
-    Ok(clean_markdown_bytes(&rewrited_bytes))
-}
+    let markdown = clean_markdown_bytes(&rewrited_bytes);
+    let markdown = pad_table_headers(markdown, &lock_state(&state).table_padding);
+    let mut markdown = wrap_markdown_opt(markdown, options);
+    markdown.push_str(&missing_note_definitions(&lock_state(&state)));
+    markdown.push_str(&link_definitions(&lock_state(&state)));
+    markdown.insert_str(0, &front_matter(&lock_state(&state)));
 
-/// Convert to markdown streaming re-writer
-#[cfg(feature = "stream")]
-pub async fn convert_html_to_markdown_send(
-    html: &str,
-    custom: &Option<std::collections::HashSet<String>>,
-    commonmark: bool,
-    url: &Option<Url>,
-) -> Result<String, Box<dyn std::error::Error>> {
-    convert_html_to_markdown_send_with_size(html, custom, commonmark, url, 8192).await
+    Ok(markdown)
 }
 
 /// Error type for stream-based conversion.
@@ -548,11 +1159,9 @@ impl<E: std::error::Error + 'static> std::error::Error for StreamConvertError<E>
 /// Genuinely async — yields to the executor between input chunks via `stream.next().await`.
 /// Uses `lol_html::send::HtmlRewriter` which handles chunk-boundary splitting internally.
 #[cfg(feature = "stream")]
-pub async fn convert_html_stream_to_markdown<S, B, E>(
+pub async fn convert_html_stream_to_markdown_with_options<S, B, E>(
     stream: S,
-    custom: &Option<std::collections::HashSet<String>>,
-    commonmark: bool,
-    url: &Option<Url>,
+    options: &ConversionOptions,
 ) -> Result<String, StreamConvertError<E>>
 where
     S: futures_util::Stream<Item = Result<B, E>> + Unpin,
@@ -560,10 +1169,11 @@ where
 {
     use futures_util::StreamExt;
 
-    let settings = get_rewriter_settings_send(commonmark, custom, url.clone());
+    let state: SharedStateSend = Arc::new(Mutex::new(RewriterState::default()));
+    let settings = rewriter_settings_send(options, state.clone());
     let mut output: Vec<u8> = Vec::with_capacity(4096);
 
-    let mut rewriter = lol_html::send::HtmlRewriter::new(settings.into(), |c: &[u8]| {
+    let mut rewriter = lol_html::send::HtmlRewriter::new(settings, |c: &[u8]| {
         output.extend_from_slice(c);
     });
 
@@ -578,5 +1188,33 @@ where
 
     rewriter.end().map_err(StreamConvertError::Rewrite)?;
 
-    Ok(clean_markdown_bytes(&output))
+    let markdown = clean_markdown_bytes(&output);
+    let markdown = pad_table_headers(markdown, &lock_state(&state).table_padding);
+    let mut markdown = wrap_markdown_opt(markdown, options);
+    markdown.push_str(&missing_note_definitions(&lock_state(&state)));
+    markdown.push_str(&link_definitions(&lock_state(&state)));
+    markdown.insert_str(0, &front_matter(&lock_state(&state)));
+
+    Ok(markdown)
+}
+
+/// Convert an async byte stream of HTML into markdown.
+#[cfg(feature = "stream")]
+#[deprecated(note = "use `convert_html_stream_to_markdown_with_options`")]
+pub async fn convert_html_stream_to_markdown<S, B, E>(
+    stream: S,
+    custom: &Option<std::collections::HashSet<String>>,
+    commonmark: bool,
+    url: &Option<url::Url>,
+) -> Result<String, StreamConvertError<E>>
+where
+    S: futures_util::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    let options = ConversionOptions::default()
+        .with_commonmark(commonmark)
+        .with_ignore(custom.clone().unwrap_or_default())
+        .with_url(url.clone());
+
+    convert_html_stream_to_markdown_with_options(stream, &options).await
 }
@@ -1,27 +1,24 @@
 use super::StructuredPrinter;
 use super::TagHandler;
+use crate::extended::base::url::resolve_url;
+use crate::{ConversionOptions, LinkStyle};
 use markup5ever_rcdom::{Handle, NodeData};
 use percent_encoding::percent_decode_str;
 use std::borrow::Cow;
 use std::sync::Arc;
-use url::Url;
 
 #[derive(Default)]
 pub struct AnchorHandler {
     start_pos: usize,
     href: String,
-    /// Used to make absolute urls.
-    url: Option<Arc<Url>>,
+    /// The conversion options, the url is used to make absolute urls.
+    options: Arc<ConversionOptions>,
 }
 
 impl AnchorHandler {
-    pub fn new(url: &Option<std::sync::Arc<Url>>) -> Self {
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
         Self {
-            url: if let Some(u) = url {
-                Some(u.clone())
-            } else {
-                None
-            },
+            options: options.clone(),
             ..Default::default()
         }
     }
@@ -50,22 +47,19 @@ impl TagHandler for AnchorHandler {
         // Percent decode url.
         let url = percent_decode_str(&self.href).decode_utf8_lossy();
 
-        let url = if url.starts_with('/') {
-            match &self.url {
-                Some(base_url) => base_url
-                    .join(&url)
-                    .map_or(url, |joined_url| joined_url.to_string().into()),
-                None => url,
-            }
-        } else {
-            url
-        };
+        let url = resolve_url(self.options.url.as_ref(), url);
 
-        // [CommonMark Spec](https://spec.commonmark.org/0.31.2/#link-destination)
-        let url = if url.contains(|c: char| c.is_ascii_control() || c == ' ') {
-            Cow::Owned(format!("<{}>", url))
+        let suffix = if self.options.link_style == LinkStyle::Referenced {
+            format!("][{}]", printer.references.reference(&url))
         } else {
-            url
+            // [CommonMark Spec](https://spec.commonmark.org/0.31.2/#link-destination)
+            let url = if url.contains(|c: char| c.is_ascii_control() || c == ' ') {
+                Cow::Owned(format!("<{}>", url))
+            } else {
+                url
+            };
+
+            format!("]({})", url)
         };
 
         match printer.data.get(self.start_pos..) {
@@ -84,18 +78,18 @@ impl TagHandler for AnchorHandler {
                     // handle end
                     if ends_new_line {
                         let next_position = printer.data.len();
-                        printer.insert_str(next_position - 1, &format!("]({})", url));
+                        printer.insert_str(next_position - 1, &suffix);
                     } else {
-                        printer.append_str(&format!("]({})", url));
+                        printer.append_str(&suffix);
                     }
                 } else {
                     printer.insert_str(self.start_pos, "[");
-                    printer.append_str(&format!("]({})", url));
+                    printer.append_str(&suffix);
                 }
             }
             _ => {
                 printer.insert_str(self.start_pos, "[");
-                printer.append_str(&format!("]({})", url));
+                printer.append_str(&suffix);
             }
         }
     }
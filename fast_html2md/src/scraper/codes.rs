@@ -1,14 +1,68 @@
+use super::common::{get_tag_attr, parent};
 use super::StructuredPrinter;
 use super::TagHandler;
+use crate::extended::base::code::{code_fence, code_language, code_span_delimiters};
+use crate::ConversionOptions;
 
 use markup5ever_rcdom::{Handle, NodeData};
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct CodeHandler {
     code_type: String,
+    /// The fence info string of a `<pre>` block.
+    language: Option<String>,
+    /// The opening and closing fence or inline delimiter.
+    delimiters: (String, String),
+    options: Arc<ConversionOptions>,
+}
+
+/// The text content of the element.
+fn text_content(tag: &Handle, out: &mut String) {
+    for child in tag.children.borrow().iter() {
+        match child.data {
+            NodeData::Text { ref contents } => out.push_str(&contents.borrow()),
+            _ => text_content(child, out),
+        }
+    }
+}
+
+/// The language declared on the element.
+fn tag_language(tag: &Handle) -> Option<String> {
+    code_language(
+        get_tag_attr(tag, "class").as_deref(),
+        get_tag_attr(tag, "data-lang")
+            .or_else(|| get_tag_attr(tag, "data-language"))
+            .as_deref(),
+    )
+}
+
+/// The language of a `<pre>` block: declared on the `<pre>`, a nested `<code>` or the enclosing element.
+fn pre_language(tag: &Handle) -> Option<String> {
+    tag_language(tag)
+        .or_else(|| {
+            tag.children
+                .borrow()
+                .iter()
+                .find_map(|child| match child.data {
+                    NodeData::Element { ref name, .. } if name.local.as_ref() == "code" => {
+                        tag_language(child)
+                    }
+                    _ => None,
+                })
+        })
+        .or_else(|| parent(tag).and_then(|parent| tag_language(&parent)))
 }
 
 impl CodeHandler {
+    /// A new code handler.
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        Self {
+            options: options.clone(),
+            ..Default::default()
+        }
+    }
+
     /// Used in both starting and finishing handling
     fn do_handle(&mut self, printer: &mut StructuredPrinter, start: bool) {
         if self.code_type == "code"
@@ -26,13 +80,19 @@ impl CodeHandler {
                 // code block should have its own paragraph
                 if start {
                     printer.insert_newline();
-                }
-                printer.append_str("\n```\n");
-                if !start {
+                    printer.append_str("\n");
+                    printer.append_str(&self.delimiters.0);
+                    printer.append_str(self.language.as_deref().unwrap_or_default());
+                    printer.append_str("\n");
+                } else {
+                    printer.append_str("\n");
+                    printer.append_str(&self.delimiters.1);
+                    printer.append_str("\n");
                     printer.insert_newline();
                 }
             }
-            "code" | "samp" => printer.append_str("`"),
+            "code" | "samp" if start => printer.append_str(&self.delimiters.0),
+            "code" | "samp" => printer.append_str(&self.delimiters.1),
             _ => (),
         }
     }
@@ -45,6 +105,19 @@ impl TagHandler for CodeHandler {
             _ => String::new(),
         };
 
+        // the fence and delimiters are longer than any backtick run of the text
+        let mut text = String::new();
+        text_content(tag, &mut text);
+
+        if self.code_type == "pre" {
+            self.language = pre_language(tag);
+
+            let fence = code_fence(&text, self.options.style.code_fence.as_char());
+            self.delimiters = (fence.clone(), fence);
+        } else if !text.is_empty() {
+            self.delimiters = code_span_delimiters(&text);
+        }
+
         self.do_handle(printer, true);
     }
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
@@ -1,4 +1,81 @@
 use markup5ever_rcdom::{Handle, NodeData};
+use std::collections::HashSet;
+
+/// The parent of the element.
+pub(crate) fn parent(tag: &Handle) -> Option<Handle> {
+    let weak = tag.parent.take();
+    let parent = weak.as_ref().and_then(|weak| weak.upgrade());
+    tag.parent.set(weak);
+    parent
+}
+
+/// The `href` of the first `<base>` element of the document.
+pub(crate) fn find_base_href(tag: &Handle) -> Option<String> {
+    match tag.data {
+        NodeData::Element { ref name, .. } if name.local.as_ref() == "base" => {
+            if let Some(href) = get_tag_attr(tag, "href") {
+                return Some(href);
+            }
+        }
+        _ => (),
+    }
+
+    tag.children.borrow().iter().find_map(find_base_href)
+}
+
+/// The element matches a simple selector: `tag`, `#id`, `.class` or a compound like `div.post`.
+pub(crate) fn matches_simple_selector(tag: &Handle, selector: &str) -> bool {
+    let NodeData::Element { ref name, .. } = tag.data else {
+        return false;
+    };
+
+    let selector = selector.trim();
+    let (tag_name, mut rest) =
+        selector.split_at(selector.find(['#', '.']).unwrap_or(selector.len()));
+
+    if !tag_name.is_empty() && tag_name != "*" && !tag_name.eq_ignore_ascii_case(&name.local) {
+        return false;
+    }
+
+    while let Some(kind) = rest.chars().next() {
+        let end = rest[1..].find(['#', '.']).map_or(rest.len(), |i| i + 1);
+        let (part, next) = rest.split_at(end);
+
+        let matched = if kind == '#' {
+            get_tag_attr(tag, "id").is_some_and(|id| id == part[1..])
+        } else {
+            get_tag_attr(tag, "class").is_some_and(|class| {
+                class
+                    .split_ascii_whitespace()
+                    .any(|class| class == &part[1..])
+            })
+        };
+
+        if !matched {
+            return false;
+        }
+
+        rest = next;
+    }
+
+    true
+}
+
+/// Collect the outermost elements matching one of the selectors in document order.
+pub(crate) fn find_included(tag: &Handle, selectors: &HashSet<String>, included: &mut Vec<Handle>) {
+    let matched = selectors
+        .iter()
+        .flat_map(|selectors| selectors.split(','))
+        .any(|selector| matches_simple_selector(tag, selector));
+
+    if matched {
+        included.push(tag.clone());
+    } else {
+        for child in tag.children.borrow().iter() {
+            find_included(child, selectors, included);
+        }
+    }
+}
 
 pub fn get_tag_attr(tag: &Handle, attr_name: &str) -> Option<String> {
     match tag.data {
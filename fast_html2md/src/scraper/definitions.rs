@@ -0,0 +1,76 @@
+use super::StructuredPrinter;
+use super::TagHandler;
+use crate::extended::base::definition::definition_prefix;
+use crate::ConversionOptions;
+use markup5ever_rcdom::{Handle, NodeData};
+use std::sync::Arc;
+
+/// Handler for `<dl>`, `<dt>` and `<dd>`: `Term` and `: Definition` lines.
+#[derive(Default)]
+pub struct DefinitionHandler {
+    commonmark: bool,
+    /// The strong delimiter of the terms in strict CommonMark.
+    strong: &'static str,
+    tag_name: String,
+    start_pos: usize,
+}
+
+impl DefinitionHandler {
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        Self {
+            commonmark: options.commonmark,
+            strong: options.style.strong.double(),
+            ..Default::default()
+        }
+    }
+}
+
+impl TagHandler for DefinitionHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        self.tag_name = match tag.data {
+            NodeData::Element { ref name, .. } => name.local.to_string(),
+            _ => String::new(),
+        };
+
+        printer.insert_newline();
+
+        match self.tag_name.as_str() {
+            "dt" if self.commonmark => printer.append_str(self.strong),
+            "dd" => printer.append_str(definition_prefix(self.commonmark)),
+            _ => (),
+        }
+
+        self.start_pos = printer.data.len();
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        // a <p> inside the definition stays on the line of the prefix
+        if self.tag_name == "dd" {
+            while matches!(
+                printer.data.as_bytes().get(self.start_pos),
+                Some(b'\n' | b' ')
+            ) {
+                printer.data.remove(self.start_pos);
+            }
+        }
+
+        // the term is written without its outer whitespace
+        if self.tag_name == "dt" {
+            while matches!(
+                printer.data.as_bytes().get(self.start_pos),
+                Some(b'\n' | b' ')
+            ) {
+                printer.data.remove(self.start_pos);
+            }
+
+            let end = printer.data.trim_end().len().max(self.start_pos);
+            printer.data.truncate(end);
+
+            if self.commonmark {
+                printer.append_str(self.strong);
+            }
+        }
+
+        printer.insert_newline();
+    }
+}
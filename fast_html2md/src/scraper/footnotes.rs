@@ -0,0 +1,86 @@
+use super::common::get_tag_attr;
+use super::StructuredPrinter;
+use super::TagHandler;
+use crate::extended::base::footnote::{is_backlink_class, is_reference_class, note_id};
+use markup5ever_rcdom::{Handle, NodeData};
+
+/// The text content of the node.
+fn text_content(tag: &Handle, text: &mut String) {
+    match tag.data {
+        NodeData::Text { ref contents } => text.push_str(&contents.borrow()),
+        _ => {
+            for child in tag.children.borrow().iter() {
+                text_content(child, text);
+            }
+        }
+    }
+}
+
+/// The note id and the text of a citation: a `<sup class="reference">` holding only an in-page link.
+pub(crate) fn citation(tag: &Handle) -> Option<(String, String)> {
+    if !get_tag_attr(tag, "class").is_some_and(|class| is_reference_class(&class)) {
+        return None;
+    }
+
+    let mut link = None;
+
+    for child in tag.children.borrow().iter() {
+        match child.data {
+            NodeData::Element { ref name, .. } if name.local.as_ref() == "a" && link.is_none() => {
+                link = Some(child.clone());
+            }
+            NodeData::Text { ref contents } if contents.borrow().trim().is_empty() => (),
+            NodeData::Comment { .. } => (),
+            _ => return None,
+        }
+    }
+
+    let link = link?;
+    let id = note_id(&get_tag_attr(&link, "href")?)?.to_string();
+    let mut text = String::new();
+    text_content(&link, &mut text);
+
+    Some((id, text))
+}
+
+/// The element is a backlink of the open note, dropped with its content.
+pub(crate) fn is_backlink(tag: &Handle, tag_name: &str, printer: &StructuredPrinter) -> bool {
+    printer.in_note
+        && match tag_name {
+            "a" => {
+                get_tag_attr(tag, "href").is_some_and(|href| printer.footnotes.is_backlink(&href))
+            }
+            "span" => get_tag_attr(tag, "class").is_some_and(|class| is_backlink_class(&class)),
+            _ => false,
+        }
+}
+
+/// Handler for the citations: `[^3]`.
+#[derive(Default)]
+pub struct CitationHandler {
+    note_id: String,
+    text: String,
+}
+
+impl CitationHandler {
+    pub fn new(note_id: String, text: String) -> Self {
+        Self { note_id, text }
+    }
+}
+
+impl TagHandler for CitationHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let label = printer
+            .footnotes
+            .cite(&self.note_id, get_tag_attr(tag, "id"), &self.text);
+        let marker = format!("[^{label}]");
+
+        printer.append_str(&marker);
+    }
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
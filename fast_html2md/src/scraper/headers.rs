@@ -1,11 +1,34 @@
 use super::StructuredPrinter;
 use super::TagHandler;
+use crate::ConversionOptions;
 
 use markup5ever_rcdom::{Handle, NodeData};
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct HeaderHandler {
     header_type: String,
+    options: Arc<ConversionOptions>,
+    /// The start of the text of the heading.
+    start_pos: usize,
+}
+
+impl HeaderHandler {
+    /// A new header handler.
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        Self {
+            options: options.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// The level of the heading, `<h3>` is 3.
+    fn level(&self) -> usize {
+        self.header_type
+            .strip_prefix('h')
+            .and_then(|level| level.parse().ok())
+            .unwrap_or_default()
+    }
 }
 
 impl TagHandler for HeaderHandler {
@@ -15,18 +38,20 @@ impl TagHandler for HeaderHandler {
             _ => String::new(),
         };
 
-        match self.header_type.as_ref() {
-            "h1" => printer.append_str("# "),
-            "h2" => printer.append_str("## "),
-            "h3" => printer.append_str("### "),
-            "h4" => printer.append_str("#### "),
-            "h5" => printer.append_str("##### "),
-            "h6" => printer.append_str("###### "),
-            _ => (),
+        if let level @ 1..=6 = self.level() {
+            printer.append_str(self.options.style.heading_prefix(level));
         }
+
+        self.start_pos = printer.data.len();
     }
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        // an empty heading has nothing to underline
+        if let Some(underline) = self.options.style.heading_underline(self.level()) {
+            if !printer.data[self.start_pos..].trim().is_empty() {
+                printer.append_str(underline);
+            }
+        }
         printer.insert_newline();
     }
 }
@@ -5,10 +5,25 @@ use super::common::get_tag_attr;
 use super::dummy::IdentityHandler;
 
 use crate::extended::base::iframe::{INSTAGRAM_PATTERN, VK_PATTERN, YOUTUBE_PATTERN};
+use crate::extended::base::url::resolve_url;
+use crate::ConversionOptions;
 use markup5ever_rcdom::Handle;
+use std::sync::Arc;
 
 #[derive(Default)]
-pub struct IframeHandler;
+pub struct IframeHandler {
+    /// The conversion options, the url is used to make absolute urls.
+    options: Arc<ConversionOptions>,
+}
+
+impl IframeHandler {
+    /// A new iframe handler.
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        Self {
+            options: options.clone(),
+        }
+    }
+}
 
 impl TagHandler for IframeHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
@@ -19,6 +34,8 @@ impl TagHandler for IframeHandler {
         //let height = get_tag_attr(tag, "height");
 
         if let Some(src) = src {
+            let src = resolve_url(self.options.url.as_ref(), src.into());
+
             if let Some(capture) = YOUTUBE_PATTERN.captures(&src) {
                 let media_id = capture.get(1).map_or("", |m| m.as_str());
                 printer.append_str(&format!("[![Embedded YouTube video](https://img.youtube.com/vi/{mid}/0.jpg)](https://www.youtube.com/watch?v={mid})", mid = media_id));
@@ -1,11 +1,13 @@
 use std::sync::Arc;
 
-use super::common::get_tag_attr;
+use super::common::{get_tag_attr, parent};
 use super::StructuredPrinter;
 use super::TagHandler;
-use markup5ever_rcdom::Handle;
+use crate::extended::base::image::ImageSources;
+use crate::extended::base::url::{resolve_srcset, resolve_url};
+use crate::ConversionOptions;
+use markup5ever_rcdom::{Handle, NodeData};
 use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
-use url::Url;
 
 const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
 
@@ -14,25 +16,40 @@ const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').ad
 #[derive(Default)]
 pub struct ImgHandler {
     block_mode: bool,
-    commonmark: bool,
-    /// Used to make absolute urls.
-    url: Option<Arc<Url>>,
+    /// The conversion options, the url is used to make absolute urls.
+    options: Arc<ConversionOptions>,
 }
 
 impl ImgHandler {
-    pub fn new(commonmark: bool, url: &Option<std::sync::Arc<Url>>) -> Self {
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
         Self {
-            commonmark,
-            url: if let Some(u) = url {
-                Some(u.clone())
-            } else {
-                None
-            },
+            options: options.clone(),
             ..Default::default()
         }
     }
 }
 
+/// The `srcset` of the first `<source>` of the enclosing `<picture>`.
+fn picture_source(tag: &Handle) -> Option<String> {
+    let picture = parent(tag)?;
+
+    match picture.data {
+        NodeData::Element { ref name, .. } if name.local.as_ref() == "picture" => (),
+        _ => return None,
+    }
+
+    let children = picture.children.borrow();
+
+    children.iter().find_map(|child| match child.data {
+        NodeData::Element { ref name, .. } if name.local.as_ref() == "source" => {
+            get_tag_attr(child, "srcset")
+                .or_else(|| get_tag_attr(child, "data-srcset"))
+                .filter(|srcset| !srcset.trim().is_empty())
+        }
+        _ => None,
+    })
+}
+
 impl TagHandler for ImgHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
         // hack: detect if the image has associated style and has display in block mode
@@ -50,22 +67,31 @@ impl TagHandler for ImgHandler {
         }
 
         // try to extract attrs
-        let src = get_tag_attr(tag, "src");
+        let sources =
+            ImageSources::from_attributes(picture_source(tag), |name| get_tag_attr(tag, name));
+        let src = sources
+            .select(self.options.image_source_policy)
+            .map(str::to_string);
 
         let alt = get_tag_attr(tag, "alt");
         let title = get_tag_attr(tag, "title");
         let height = get_tag_attr(tag, "height");
         let width = get_tag_attr(tag, "width");
         let align = get_tag_attr(tag, "align");
+        let base = self.options.url.as_ref();
 
-        if self.commonmark && (height.is_some() || width.is_some() || align.is_some()) {
+        if self.options.commonmark && (height.is_some() || width.is_some() || align.is_some()) {
             // need to handle it as inline html to preserve attributes we support
             printer.append_str(&format!(
                 "<img{} />",
                 alt.map(|value| format!(" alt=\"{}\"", value))
                     .unwrap_or_default()
-                    + &src
-                        .map(|value| format!(" src=\"{}\"", value))
+                    + &sources
+                        .fallback()
+                        .map(|value| format!(" src=\"{}\"", resolve_url(base, value.into())))
+                        .unwrap_or_default()
+                    + &get_tag_attr(tag, "srcset")
+                        .map(|value| format!(" srcset=\"{}\"", resolve_srcset(base, &value)))
                         .unwrap_or_default()
                     + &title
                         .map(|value| format!(" title=\"{}\"", value))
@@ -89,13 +115,7 @@ impl TagHandler for ImgHandler {
                 img_url = utf8_percent_encode(&img_url, FRAGMENT).to_string();
             }
 
-            if img_url.starts_with("/") {
-                if let Some(ref u) = self.url {
-                    if let Ok(n) = u.join(&img_url) {
-                        img_url = n.to_string();
-                    }
-                }
-            }
+            let img_url = resolve_url(base, img_url.into());
 
             printer.append_str(&format!(
                 "![{}]({}{})",
@@ -1,7 +1,11 @@
+use super::common::get_tag_attr;
 use super::StructuredPrinter;
 use super::TagHandler;
+use crate::extended::base::task::{checkbox_marker, is_checkbox};
+use crate::ConversionOptions;
 
 use markup5ever_rcdom::Handle;
+use std::sync::Arc;
 
 /// gets all list elements registered by a `StructuredPrinter` in reverse order
 fn list_hierarchy(printer: &mut StructuredPrinter) -> Vec<&String> {
@@ -37,10 +41,23 @@ impl TagHandler for ListHandler {
 pub struct ListItemHandler {
     start_pos: usize,
     list_type: String,
+    /// The item is a cited footnote definition.
+    note: bool,
+    options: Arc<ConversionOptions>,
+}
+
+impl ListItemHandler {
+    /// A new list item handler.
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        Self {
+            options: options.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 impl TagHandler for ListItemHandler {
-    fn handle(&mut self, _tag: &Handle, printer: &mut StructuredPrinter) {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
         {
             let parent_lists = list_hierarchy(printer);
             let nearest_parent_list = parent_lists.first();
@@ -60,6 +77,18 @@ impl TagHandler for ListItemHandler {
             printer.insert_newline();
         }
 
+        // a cited item is the definition of its footnote: [^3]: Text
+        let label = get_tag_attr(tag, "id")
+            .and_then(|id| printer.footnotes.define(&id).map(|label| label.to_string()));
+
+        if let Some(label) = label {
+            printer.append_str(&format!("[^{label}]: "));
+            printer.in_note = true;
+            self.note = true;
+            self.start_pos = printer.data.len();
+            return;
+        }
+
         let current_depth = printer.parent_chain.len();
 
         let order = if printer.siblings.len() >= current_depth {
@@ -69,9 +98,13 @@ impl TagHandler for ListItemHandler {
         };
 
         match self.list_type.as_ref() {
-            "ul" | "menu" => printer.append_str("* "), // unordered list: *, *, *
+            // unordered list: *, *, *
+            "ul" | "menu" => {
+                printer.append_str(self.options.style.bullet.as_str());
+                printer.append_str(" ");
+            }
             "ol" => printer.append_str(&(order.to_string() + ". ")), // ordered list: 1, 2, 3
-            _ => (),                                   // never happens
+            _ => (),                                                 // never happens
         }
 
         self.start_pos = printer.data.len();
@@ -79,11 +112,16 @@ impl TagHandler for ListItemHandler {
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
         let padding = match self.list_type.as_ref() {
+            _ if self.note => 4,
             "ul" => 2,
             "ol" => 3,
             _ => 4,
         };
 
+        if self.note {
+            printer.in_note = false;
+        }
+
         // need to cleanup leading newlines, <p> inside <li> should produce valid
         // list element, not an empty line
         let index = self.start_pos;
@@ -106,5 +144,45 @@ impl TagHandler for ListItemHandler {
             }
             index -= 1;
         }
+
+        // the text after the note is not part of it
+        if self.note {
+            printer.append_str("\n\n");
+        }
     }
 }
+
+/// Nothing but the marker of a list item is written on the line yet.
+fn at_item_start(printer: &StructuredPrinter) -> bool {
+    let data = printer.data.trim_end();
+    let line = data[data.rfind('\n').map_or(0, |i| i + 1)..].trim_start();
+    let marker = matches!(line, "*" | "-" | "+")
+        || line
+            .strip_suffix('.')
+            .is_some_and(|order| !order.is_empty() && order.bytes().all(|b| b.is_ascii_digit()));
+
+    marker && printer.parent_chain.iter().any(|tag| tag == "li")
+}
+
+/// Handler for the checkbox `<input>`: a task `[x] ` at the start of a list item, `[x]` elsewhere.
+#[derive(Default)]
+pub struct CheckboxHandler;
+
+impl TagHandler for CheckboxHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if !is_checkbox(get_tag_attr(tag, "type").as_deref()) {
+            return;
+        }
+
+        let marker = checkbox_marker(get_tag_attr(tag, "checked").is_some());
+
+        if at_item_start(printer) {
+            printer.append_str(marker);
+            printer.append_str(" ");
+        } else {
+            printer.append_str(marker);
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
@@ -0,0 +1,89 @@
+use super::common::get_tag_attr;
+use super::StructuredPrinter;
+use super::TagHandler;
+use crate::extended::base::math::{is_math_rendering, math_script_display, MathBuilder};
+use markup5ever_rcdom::{Handle, NodeData};
+
+/// The element is a MathJax `<script type="math/tex">`.
+pub(crate) fn is_math_script(node: &NodeData) -> bool {
+    match node {
+        NodeData::Element {
+            ref name,
+            ref attrs,
+            ..
+        } => {
+            name.local.as_ref() == "script"
+                && attrs.borrow().iter().any(|attr| {
+                    attr.name.local.as_ref() == "type" && math_script_display(&attr.value).is_some()
+                })
+        }
+        _ => false,
+    }
+}
+
+/// The math scripts of the `<head>`, html5ever moves the scripts written before the body there.
+pub(crate) fn head_math_scripts(node: &Handle) -> Vec<Handle> {
+    match node.data {
+        NodeData::Element { ref name, .. } if name.local.as_ref() == "head" => node
+            .children
+            .borrow()
+            .iter()
+            .filter(|child| is_math_script(&child.data))
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The element is a rendering of the math next to its source.
+pub(crate) fn is_rendering(tag: &Handle, tag_name: &str) -> bool {
+    is_math_rendering(tag_name, get_tag_attr(tag, "class").as_deref())
+}
+
+/// Read the element and its content into the math.
+fn read_math(tag: &Handle, math: &mut MathBuilder) {
+    match tag.data {
+        NodeData::Element {
+            ref name,
+            ref attrs,
+            ..
+        } => {
+            let attributes = attrs
+                .borrow()
+                .iter()
+                .map(|attr| (attr.name.local.to_string(), attr.value.to_string()))
+                .collect();
+
+            math.open(&name.local, attributes);
+
+            for child in tag.children.borrow().iter() {
+                read_math(child, math);
+            }
+
+            math.close();
+        }
+        NodeData::Text { ref contents } => math.text(&contents.borrow()),
+        _ => (),
+    }
+}
+
+/// Handler for `<math>` and the MathJax scripts: `$x^2$` or `$$x^2$$`.
+#[derive(Default)]
+pub struct MathHandler;
+
+impl TagHandler for MathHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let mut math = MathBuilder::default();
+        read_math(tag, &mut math);
+
+        if let Some(markdown) = math.into_markdown() {
+            printer.append_str(&markdown);
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
@@ -2,34 +2,47 @@ pub mod anchors;
 pub mod codes;
 pub mod common;
 pub mod containers;
+pub mod definitions;
 pub mod dummy;
+pub mod footnotes;
 pub mod headers;
 pub mod iframes;
 pub mod ignore;
 pub mod images;
 pub mod lists;
+pub mod math;
 pub mod paragraphs;
 pub mod quotes;
+pub(crate) mod readability;
 pub mod styles;
 pub mod tables;
 pub mod utils;
-use super::clean_markdown;
+use super::{clean_markdown, wrap_markdown_opt};
+use crate::extended::base::footnote::Footnotes;
+use crate::extended::base::link::LinkReferences;
+use crate::extended::base::url::document_base_url;
+use crate::ConversionOptions;
 use anchors::AnchorHandler;
 use codes::CodeHandler;
 use containers::ContainerHandler;
+use definitions::DefinitionHandler;
 use dummy::DummyHandler;
 use dummy::HtmlCherryPickHandler;
 use dummy::IdentityHandler;
+use footnotes::CitationHandler;
 use headers::HeaderHandler;
 use html5ever::driver::ParseOpts;
 use html5ever::parse_document;
 use html5ever::tendril::TendrilSink;
 use iframes::IframeHandler;
+use ignore::IgnoreTagFactory;
 use images::ImgHandler;
 use lazy_static::lazy_static;
+use lists::CheckboxHandler;
 use lists::ListHandler;
 use lists::ListItemHandler;
 use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use math::MathHandler;
 use paragraphs::ParagraphHandler;
 use quotes::QuoteHandler;
 use regex::Regex;
@@ -53,34 +66,49 @@ lazy_static! {
 /// # Arguments
 /// `html` is source HTML as `String`
 /// `custom` is custom tag hadler producers for tags you want, can be empty
-/// `commonmark` is for adjusting markdown output to commonmark
+/// `options` controls the markdown output
 pub fn parse_html_custom_base(
     html: &str,
     custom: &HashMap<String, Box<dyn TagHandlerFactory>>,
-    commonmark: bool,
-    url: &Option<Url>,
+    options: &ConversionOptions,
 ) -> String {
     let document_parser = parse_document(RcDom::default(), ParseOpts::default());
 
     match document_parser.from_utf8().read_from(&mut html.as_bytes()) {
         Ok(dom) => {
             let mut result = Box::new(StructuredPrinter::default());
+            let mut options = options.clone();
 
-            walk(
-                &dom.document,
-                &mut result,
-                custom,
-                commonmark,
-                &if let Some(u) = url {
-                    Some(Arc::new(u.clone()))
-                } else {
-                    None
-                },
-                false,
-            );
+            // a <base href> is the base of every relative url of the document
+            if let Some(href) = common::find_base_href(&dom.document) {
+                if let Some(url) = document_base_url(options.url.as_ref(), &href) {
+                    options.url = Some(url);
+                }
+            }
+
+            // the included elements or the article and the blocks around it are converted
+            let roots = if !options.include.is_empty() {
+                let mut included = Vec::new();
+                common::find_included(&dom.document, &options.include, &mut included);
+                Some(included)
+            } else if options.main_content {
+                readability::main_content(&dom.document)
+            } else {
+                None
+            };
+            let roots = roots.unwrap_or_else(|| vec![dom.document.clone()]);
+            let options = Arc::new(options);
+
+            for root in roots.iter() {
+                walk(root, &mut result, custom, &options, false);
+            }
 
             // we want to eventually remove the clean step.
-            clean_markdown(&result.data)
+            let mut markdown = wrap_markdown_opt(clean_markdown(&result.data), &options);
+            // after the cleanup, the link definitions need the blank line before them
+            markdown.push_str(&result.footnotes.missing_definitions());
+            markdown.push_str(&result.references.definitions());
+            markdown
         }
         _ => Default::default(),
     }
@@ -99,7 +127,11 @@ pub fn parse_html_custom(
     custom: &HashMap<String, Box<dyn TagHandlerFactory>>,
     commonmark: bool,
 ) -> String {
-    parse_html_custom_base(html, custom, commonmark, &None)
+    parse_html_custom_base(
+        html,
+        custom,
+        &ConversionOptions::default().with_commonmark(commonmark),
+    )
 }
 
 /// Custom variant of main function. Allows to pass custom tag<->tag factory pairs
@@ -117,7 +149,19 @@ pub fn parse_html_custom_with_url(
     commonmark: bool,
     url: &Option<Url>,
 ) -> String {
-    parse_html_custom_base(html, custom, commonmark, &url)
+    parse_html_custom_base(
+        html,
+        custom,
+        &ConversionOptions::from_parts(&None, commonmark, url),
+    )
+}
+
+/// Parses incoming HTML and converts it into Markdown using the conversion options.
+/// # Arguments
+/// `html` is source HTML as `String`
+/// `options` controls the markdown output
+pub fn parse_html_with_options(html: &str, options: &ConversionOptions) -> String {
+    parse_html_custom_base(html, &HashMap::default(), options)
 }
 
 /// Main function of this library. Parses incoming HTML, converts it into Markdown
@@ -161,12 +205,12 @@ fn escape_markdown(result: &StructuredPrinter, text: &str) -> String {
 /// `input` is DOM tree or its subtree
 /// `result` is output holder with position and context tracking
 /// `custom` is custom tag hadler producers for tags you want, can be empty
+/// `options` controls the markdown output
 pub fn walk(
     input: &Handle,
     result: &mut StructuredPrinter,
     custom: &HashMap<String, Box<dyn TagHandlerFactory>>,
-    commonmark: bool,
-    url: &Option<Arc<Url>>,
+    options: &Arc<ConversionOptions>,
     ignore_parents: bool,
 ) {
     let mut handler: Box<dyn TagHandler> = Box::new(DummyHandler);
@@ -230,11 +274,13 @@ pub fn walk(
             }
         }
         NodeData::Element { ref name, .. } => {
-            if !utils::inline_elements::SKIP_ELEMENTS.contains(&name.local) {
+            if valid_block_element(&input.data) {
                 tag_name = name.local.to_string();
 
-                // do not parse scripts or style tags
-                if tag_name == "script" || tag_name == "style" {
+                // do not parse scripts or style tags, the TeX of the math scripts is kept
+                if (tag_name == "script" && !math::is_math_script(&input.data))
+                    || tag_name == "style"
+                {
                     return;
                 }
 
@@ -245,14 +291,26 @@ pub fn walk(
                 handler = if inside_pre {
                     // don't add any html tags inside the pre section
                     Box::new(DummyHandler)
+                } else if options.ignore.contains(&tag_name)
+                    || footnotes::is_backlink(input, &tag_name, result)
+                    || math::is_rendering(input, &tag_name)
+                {
+                    Box::new(IgnoreTagFactory)
+                } else if tag_name == "math" || tag_name == "script" {
+                    Box::new(MathHandler)
+                } else if let Some((note_id, text)) = (tag_name == "sup")
+                    .then(|| footnotes::citation(input))
+                    .flatten()
+                {
+                    Box::new(CitationHandler::new(note_id, text))
                 } else {
-                    get_handler(custom, &tag_name, commonmark, url)
+                    get_handler(custom, &tag_name, options)
                 }
             }
         }
     }
 
-    if !inside_table || ignore_parents && inside_table {
+    if !inside_table || ignore_parents {
         // handle this tag, while it's not in parent chain
         // and doesn't have child siblings
         handler.handle(input, result);
@@ -268,13 +326,17 @@ pub fn walk(
     if !handler.skip_descendants() {
         for child in input.children.borrow().iter() {
             if valid_block_element(&child.data) {
-                walk(&child, result, custom, commonmark, url, ignore_parents);
+                walk(child, result, custom, options, ignore_parents);
 
                 if let NodeData::Element { ref name, .. } = child.data {
                     if let Some(el) = result.siblings.get_mut(&current_depth) {
                         el.push(name.local.to_string());
                     }
                 }
+            } else {
+                for script in math::head_math_scripts(child) {
+                    walk(&script, result, custom, options, ignore_parents);
+                }
             }
         }
     }
@@ -295,7 +357,7 @@ fn escape_markdown_base(result: &str, text: &str) -> String {
     let data: std::borrow::Cow<str> = crate::MARKDOWN_MIDDLE_KEYCHARS.replace_all(text, "\\$0");
 
     // if we're at the start of the line we need to escape list- and quote-starting sequences
-    let data = if START_OF_LINE_PATTERN.is_match(&result) {
+    let data = if START_OF_LINE_PATTERN.is_match(result) {
         MARKDOWN_STARTONLY_KEYCHARS.replace(&data, "$1\\$2")
     } else {
         data
@@ -310,9 +372,9 @@ fn escape_markdown_base(result: &str, text: &str) -> String {
 pub(crate) fn get_handler<T: std::borrow::Borrow<str> + std::hash::Hash + std::cmp::Eq>(
     custom: &HashMap<String, Box<dyn TagHandlerFactory>>,
     tag_name: &T,
-    commonmark: bool,
-    url: &Option<Arc<Url>>,
+    options: &Arc<ConversionOptions>,
 ) -> Box<dyn TagHandler> {
+    let commonmark = options.commonmark;
     let name = tag_name.borrow();
     match custom.get(name) {
         Some(factory) => {
@@ -320,30 +382,32 @@ pub(crate) fn get_handler<T: std::borrow::Borrow<str> + std::hash::Hash + std::c
             factory.instantiate()
         }
         _ => {
-            match name.as_ref() {
+            match name {
                 // containers
                 "div" | "section" | "header" | "footer" => Box::new(ContainerHandler),
                 // pagination, breaks
-                "p" | "br" | "hr" => Box::new(ParagraphHandler::default()),
+                "p" | "br" | "hr" => Box::new(ParagraphHandler::new(options)),
                 "q" | "cite" | "blockquote" => Box::new(QuoteHandler::default()),
                 // spoiler tag
                 "details" | "summary" => Box::new(HtmlCherryPickHandler::new(commonmark)),
                 // formatting
-                "b" | "i" | "s" | "strong" | "em" | "del" => Box::new(StyleHandler::default()),
-                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Box::new(HeaderHandler::default()),
-                "pre" | "code" => Box::new(CodeHandler::default()),
+                "b" | "i" | "s" | "strong" | "em" | "del" => Box::new(StyleHandler::new(options)),
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Box::new(HeaderHandler::new(options)),
+                "pre" | "code" => Box::new(CodeHandler::new(options)),
                 // images, links
-                "img" => Box::new(ImgHandler::new(commonmark, url)),
-                "a" => Box::new(AnchorHandler::new(url)),
+                "img" => Box::new(ImgHandler::new(options)),
+                "a" => Box::new(AnchorHandler::new(options)),
                 // lists
                 "ol" | "ul" | "menu" => Box::new(ListHandler),
-                "li" => Box::new(ListItemHandler::default()),
+                "li" => Box::new(ListItemHandler::new(options)),
+                "input" => Box::new(CheckboxHandler),
+                "dl" | "dt" | "dd" => Box::new(DefinitionHandler::new(options)),
                 // as-is
                 "sub" | "sup" => Box::new(IdentityHandler::new(commonmark)),
                 // tables, handled fully internally as markdown can't have nested content in tables
                 // supports only single tables as of now
-                "table" => Box::new(TableHandler::new(commonmark, url.clone())),
-                "iframe" => Box::new(IframeHandler),
+                "table" => Box::new(TableHandler::new(options)),
+                "iframe" => Box::new(IframeHandler::new(options)),
                 _ => Box::new(DummyHandler),
             }
         }
@@ -355,6 +419,7 @@ pub(crate) fn valid_block_element(node: &NodeData) -> bool {
     match node {
         NodeData::Element { ref name, .. } => {
             !utils::inline_elements::SKIP_ELEMENTS.contains(&name.local)
+                || math::is_math_script(node)
         }
         _ => true,
     }
@@ -372,6 +437,12 @@ pub struct StructuredPrinter {
     pub siblings: HashMap<usize, Vec<String>>,
     /// resulting markdown document
     pub data: String,
+    /// The urls of the reference links.
+    pub(crate) references: LinkReferences,
+    /// The footnotes of the citations.
+    pub(crate) footnotes: Footnotes,
+    /// A footnote definition is open.
+    pub(crate) in_note: bool,
 }
 
 impl StructuredPrinter {
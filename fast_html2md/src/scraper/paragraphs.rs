@@ -1,11 +1,24 @@
 use super::StructuredPrinter;
 use super::TagHandler;
+use crate::ConversionOptions;
 
 use markup5ever_rcdom::{Handle, NodeData};
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct ParagraphHandler {
     paragraph_type: String,
+    options: Arc<ConversionOptions>,
+}
+
+impl ParagraphHandler {
+    /// A new paragraph handler.
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        Self {
+            options: options.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 impl TagHandler for ParagraphHandler {
@@ -26,11 +39,7 @@ impl TagHandler for ParagraphHandler {
             "p" => {
                 printer.insert_newline();
             }
-            "hr" => {
-                printer.insert_newline();
-                printer.append_str("---");
-                printer.insert_newline();
-            }
+            "hr" => printer.append_str(&self.options.style.thematic_break_block()),
             "br" => printer.insert_newline(), // we prob want nbsp here.
             _ => (),
         }
@@ -0,0 +1,358 @@
+use super::common::{get_tag_attr, parent};
+use lazy_static::lazy_static;
+use markup5ever_rcdom::{Handle, NodeData};
+use regex::Regex;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+lazy_static! {
+    /// The class or id of the page chrome around the article.
+    static ref UNLIKELY_CANDIDATES: Regex = Regex::new(r"(?i)-ad-|ad-break|agegate|banner|breadcrumbs|combx|comment|community|cover-wrap|disqus|extra|footer|gdpr|header|legends|menu|newsletter|pager|pagination|popup|\brail|recommend|related|remark|replies|rss|share|shoutbox|sidebar|skyscraper|social|sponsor|supplemental|widget").expect("valid regex pattern");
+    /// The class or id that keeps an unlikely candidate.
+    static ref MAYBE_CANDIDATES: Regex = Regex::new(r"(?i)and|article|body|column|content|main|shadow").expect("valid regex pattern");
+    /// The class or id of the containers of the article.
+    static ref POSITIVE_HINTS: Regex = Regex::new(r"(?i)article|blog|body|content|entry|h-entry|hentry|main|page|post|story|text").expect("valid regex pattern");
+    /// The class or id of the containers around the article.
+    static ref NEGATIVE_HINTS: Regex = Regex::new(r"(?i)banner|com-|comment|contact|masthead|outbrain|promo|\brail|recommend|related|share|shoutbox|sidebar|skyscraper|social|sponsor|shopping|subscribe|tags|tool|widget").expect("valid regex pattern");
+}
+
+/// The text of a subtree.
+#[derive(Debug, Default, Clone, Copy)]
+struct TextStats {
+    /// The characters of the text, whitespace collapsed.
+    chars: usize,
+    /// The characters of the text inside links.
+    link_chars: usize,
+    /// The commas of the text.
+    commas: usize,
+}
+
+impl TextStats {
+    /// Measure the text of the subtree.
+    fn of(node: &Handle) -> Self {
+        let mut stats = Self::default();
+        stats.add(node, false);
+        stats
+    }
+
+    fn add(&mut self, node: &Handle, in_link: bool) {
+        let in_link = match node.data {
+            NodeData::Text { ref contents } => {
+                let contents = contents.borrow();
+                let chars = contents
+                    .split_whitespace()
+                    .map(|word| word.chars().count() + 1)
+                    .sum::<usize>();
+
+                self.chars += chars;
+                self.commas += contents.matches(',').count();
+                if in_link {
+                    self.link_chars += chars;
+                }
+                return;
+            }
+            NodeData::Element { ref name, .. } => match name.local.as_ref() {
+                "script" | "style" | "noscript" | "template" => return,
+                tag => in_link || tag == "a",
+            },
+            _ => in_link,
+        };
+
+        for child in node.children.borrow().iter() {
+            self.add(child, in_link);
+        }
+    }
+
+    /// The share of the text inside links.
+    fn link_density(&self) -> f64 {
+        if self.chars == 0 {
+            0.0
+        } else {
+            self.link_chars as f64 / self.chars as f64
+        }
+    }
+}
+
+/// A container scored by the paragraphs inside it.
+struct Candidate {
+    node: Handle,
+    score: f64,
+}
+
+/// The tag name of the element.
+fn tag_name(node: &Handle) -> Option<&str> {
+    match node.data {
+        NodeData::Element { ref name, .. } => Some(name.local.as_ref()),
+        _ => None,
+    }
+}
+
+/// The class and id of the element.
+fn class_and_id(node: &Handle) -> String {
+    let class = get_tag_attr(node, "class").unwrap_or_default();
+    let id = get_tag_attr(node, "id").unwrap_or_default();
+
+    format!("{class} {id}")
+}
+
+/// The weight of the class and id hints of the element.
+fn class_weight(node: &Handle) -> f64 {
+    let hints = class_and_id(node);
+    let mut weight = 0.0;
+
+    if NEGATIVE_HINTS.is_match(&hints) {
+        weight -= 25.0;
+    }
+    if POSITIVE_HINTS.is_match(&hints) {
+        weight += 25.0;
+    }
+
+    weight
+}
+
+/// The element is page chrome: navigation, sidebars, comments.
+fn is_unlikely(node: &Handle, tag: &str) -> bool {
+    match tag {
+        "html" | "body" | "article" | "main" | "a" => false,
+        "nav" | "aside" | "footer" => true,
+        _ => {
+            let role = get_tag_attr(node, "role").unwrap_or_default();
+
+            if matches!(
+                role.as_str(),
+                "navigation" | "complementary" | "contentinfo" | "banner"
+            ) {
+                return true;
+            }
+
+            let hints = class_and_id(node);
+
+            UNLIKELY_CANDIDATES.is_match(&hints) && !MAYBE_CANDIDATES.is_match(&hints)
+        }
+    }
+}
+
+/// Drop the page chrome inside the content.
+fn remove_unlikely(node: &Handle) {
+    node.children
+        .borrow_mut()
+        .retain(|child| !tag_name(child).is_some_and(|tag| is_unlikely(child, tag)));
+
+    for child in node.children.borrow().iter() {
+        remove_unlikely(child);
+    }
+}
+
+/// The element holds other blocks.
+fn has_block_children(node: &Handle) -> bool {
+    node.children.borrow().iter().any(|child| {
+        tag_name(child).is_some_and(|tag| {
+            matches!(
+                tag,
+                "address"
+                    | "article"
+                    | "blockquote"
+                    | "dl"
+                    | "div"
+                    | "figure"
+                    | "form"
+                    | "h1"
+                    | "h2"
+                    | "h3"
+                    | "h4"
+                    | "h5"
+                    | "h6"
+                    | "main"
+                    | "ol"
+                    | "p"
+                    | "pre"
+                    | "section"
+                    | "table"
+                    | "ul"
+            )
+        }) || has_block_children(child)
+    })
+}
+
+/// Collect the paragraphs of the tree, the blocks of text that are scored.
+fn collect_paragraphs(node: &Handle, paragraphs: &mut Vec<Handle>) {
+    let paragraph = match tag_name(node) {
+        Some(tag) if is_unlikely(node, tag) => return,
+        Some("p" | "pre") => true,
+        Some("td" | "div" | "section") => !has_block_children(node),
+        Some("head" | "script" | "style" | "noscript" | "template") => return,
+        _ => false,
+    };
+
+    if paragraph {
+        paragraphs.push(node.clone());
+    } else {
+        for child in node.children.borrow().iter() {
+            collect_paragraphs(child, paragraphs);
+        }
+    }
+}
+
+/// The score of a container before its paragraphs are counted.
+fn initial_score(node: &Handle, tag: &str) -> f64 {
+    let score = match tag {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    score + class_weight(node)
+}
+
+/// Score the ancestors of every paragraph by its text.
+fn score_candidates(document: &Handle) -> Vec<Candidate> {
+    let mut paragraphs = Vec::new();
+    collect_paragraphs(document, &mut paragraphs);
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    let mut index = HashMap::new();
+
+    for paragraph in paragraphs.iter() {
+        let stats = TextStats::of(paragraph);
+
+        if stats.chars < 25 {
+            continue;
+        }
+
+        let score = 1.0 + stats.commas as f64 + (stats.chars / 100).min(3) as f64;
+        let mut ancestor = parent(paragraph);
+
+        // the parent gets the score, the ancestors above it a share of it
+        for level in 0..5 {
+            let Some(node) = ancestor else {
+                break;
+            };
+            let Some(tag) = tag_name(&node) else {
+                break;
+            };
+
+            let i = *index.entry(Rc::as_ptr(&node)).or_insert_with(|| {
+                candidates.push(Candidate {
+                    node: node.clone(),
+                    score: initial_score(&node, tag),
+                });
+                candidates.len() - 1
+            });
+
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                level => level as f64 * 3.0,
+            };
+
+            candidates[i].score += score / divider;
+            ancestor = parent(&node);
+        }
+    }
+
+    for candidate in candidates.iter_mut() {
+        candidate.score *= 1.0 - TextStats::of(&candidate.node).link_density();
+    }
+
+    candidates
+}
+
+/// The container holds more links than text or is hinted as boilerplate.
+fn is_boilerplate(node: &Handle) -> bool {
+    if !tag_name(node)
+        .is_some_and(|tag| matches!(tag, "div" | "section" | "ul" | "ol" | "form" | "table"))
+    {
+        return false;
+    }
+
+    let weight = class_weight(node);
+
+    if weight < 0.0 {
+        return true;
+    }
+
+    let link_density = TextStats::of(node).link_density();
+
+    (weight < 25.0 && link_density > 0.5) || link_density > 0.75
+}
+
+/// Drop the link lists and hinted boilerplate left inside the content.
+fn clean_conditionally(node: &Handle) {
+    node.children
+        .borrow_mut()
+        .retain(|child| !is_boilerplate(child));
+
+    for child in node.children.borrow().iter() {
+        clean_conditionally(child);
+    }
+}
+
+/// The main content of the document: the best scored container and its related siblings.
+///
+/// The page chrome is removed from the content, `None` when no container holds enough text.
+pub(crate) fn main_content(document: &Handle) -> Option<Vec<Handle>> {
+    let candidates = score_candidates(document);
+    let top = candidates.iter().reduce(|top, candidate| {
+        if candidate.score > top.score {
+            candidate
+        } else {
+            top
+        }
+    })?;
+
+    let score_of = |node: &Handle| {
+        candidates
+            .iter()
+            .find(|candidate| Rc::ptr_eq(&candidate.node, node))
+            .map_or(0.0, |candidate| candidate.score)
+    };
+
+    let threshold = (top.score * 0.2).max(10.0);
+    let top_class = get_tag_attr(&top.node, "class").filter(|class| !class.trim().is_empty());
+
+    let nodes = match parent(&top.node).filter(|parent| tag_name(parent).is_some()) {
+        Some(parent) => parent
+            .children
+            .borrow()
+            .iter()
+            .filter(|sibling| {
+                if Rc::ptr_eq(sibling, &top.node) {
+                    return true;
+                }
+
+                let Some(tag) = tag_name(sibling).filter(|tag| !is_unlikely(sibling, tag)) else {
+                    return false;
+                };
+
+                let bonus = if top_class.is_some() && get_tag_attr(sibling, "class") == top_class {
+                    top.score * 0.2
+                } else {
+                    0.0
+                };
+
+                if score_of(sibling) + bonus >= threshold {
+                    return true;
+                }
+
+                let stats = TextStats::of(sibling);
+                let link_density = stats.link_density();
+
+                tag == "p"
+                    && ((stats.chars > 80 && link_density < 0.25)
+                        || (stats.chars > 0 && link_density == 0.0))
+            })
+            .cloned()
+            .collect(),
+        None => vec![top.node.clone()],
+    };
+
+    for node in nodes.iter() {
+        remove_unlikely(node);
+        clean_conditionally(node);
+    }
+
+    Some(nodes)
+}
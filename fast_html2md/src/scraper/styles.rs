@@ -1,11 +1,24 @@
 use super::StructuredPrinter;
 use super::TagHandler;
+use crate::ConversionOptions;
 use markup5ever_rcdom::{Handle, NodeData};
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct StyleHandler {
     start_pos: usize,
     style_type: String,
+    options: Arc<ConversionOptions>,
+}
+
+impl StyleHandler {
+    /// A new style handler.
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        Self {
+            options: options.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 /// Applies givem `mark` at both start and end indices, updates printer position to the end of text
@@ -34,14 +47,22 @@ impl TagHandler for StyleHandler {
                 .trim_end_matches(|ch: char| ch.is_whitespace())
                 .len();
 
+            let style = &self.options.style;
+
             // finishing markup
             match self.style_type.as_ref() {
-                "b" | "strong" => {
-                    apply_at_bounds(printer, first_non_space_pos, last_non_space_pos, "**")
-                }
-                "i" | "em" => {
-                    apply_at_bounds(printer, first_non_space_pos, last_non_space_pos, "*")
-                }
+                "b" | "strong" => apply_at_bounds(
+                    printer,
+                    first_non_space_pos,
+                    last_non_space_pos,
+                    style.strong.double(),
+                ),
+                "i" | "em" => apply_at_bounds(
+                    printer,
+                    first_non_space_pos,
+                    last_non_space_pos,
+                    style.emphasis.single(),
+                ),
                 "s" | "del" => {
                     apply_at_bounds(printer, first_non_space_pos, last_non_space_pos, "~~")
                 }
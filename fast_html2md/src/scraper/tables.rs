@@ -1,17 +1,21 @@
+use super::common::get_tag_attr;
 use super::StructuredPrinter;
 use super::TagHandler;
 use super::{clean_markdown, walk};
+use crate::extended::base::link::LinkReferences;
+use crate::extended::base::table::{
+    parse_span, resolve_alignments, Alignment, ColumnGroup, RowSpans,
+};
+use crate::{ConversionOptions, SpanPolicy};
 use std::sync::Arc;
 use std::{cmp, collections::HashMap};
 
 use html5ever::LocalName;
 use markup5ever_rcdom::{Handle, NodeData};
-use url::Url;
 
 #[derive(Default)]
 pub struct TableHandler {
-    commonmark: bool,
-    url: Option<Arc<Url>>,
+    options: Arc<ConversionOptions>,
 }
 
 const TD: LocalName = html5ever::local_name!("td");
@@ -21,8 +25,10 @@ const TABLE_LIMIT: usize = 1000;
 
 impl TableHandler {
     /// A new table handler.
-    pub fn new(commonmark: bool, url: Option<std::sync::Arc<Url>>) -> Self {
-        TableHandler { commonmark, url }
+    pub fn new(options: &Arc<ConversionOptions>) -> Self {
+        TableHandler {
+            options: options.clone(),
+        }
     }
 }
 
@@ -30,66 +36,51 @@ impl TagHandler for TableHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
         let mut table_markup = String::new();
 
-        let any_matcher = |cell: &Handle| match cell.data {
-            NodeData::Element { ref name, .. } => name.local == TD || name.local == TH,
-            _ => false,
-        };
-
-        // detect cell width, counts
-        let mut column_widths: Vec<usize> = Vec::new();
         let rows = find_children(tag, "tr");
 
-        // detect row count
-        let most_big_row = rows.iter().max_by(|left, right| {
-            collect_children(left, any_matcher)
-                .len()
-                .cmp(&collect_children(right, any_matcher).len())
-        });
-
-        if most_big_row.is_some() {
-            let column_count = match most_big_row {
-                Some(tag) => collect_children(tag, any_matcher).len(),
-                _ => 0,
-            };
+        // expand the spans into a rectangular grid
+        let grid = build_grid(&rows, &self.options, &mut printer.references);
+        let column_count = grid.iter().map(Vec::len).max().unwrap_or_default();
 
-            column_widths = vec![3; column_count];
+        if !rows.is_empty() {
+            // detect cell width
+            let mut column_widths: Vec<usize> = vec![3; column_count];
 
             // detect max column width
-            for (idx, row) in rows.iter().enumerate() {
-                if idx >= TABLE_LIMIT {
-                    break;
-                }
-
-                let cells = collect_children(row, any_matcher);
-
-                for index in 0..column_count {
-                    // from regular rows
-                    if let Some(cell) = cells.get(index) {
-                        let text = to_text(cell, self.commonmark, &self.url);
-
-                        column_widths[index] = cmp::max(column_widths[index], text.chars().count());
+            for row in grid.iter() {
+                for (width, cell) in column_widths.iter_mut().zip(row.iter()) {
+                    if let Some(text) = cell {
+                        *width = cmp::max(*width, text.chars().count());
                     }
                 }
             }
 
-            // header row must always be present
-            for (idx, row) in rows.iter().enumerate() {
-                if idx >= TABLE_LIMIT {
-                    break;
-                }
+            // detect alignment from the header cells, then from the col declarations
+            let header_alignments: Vec<Alignment> = rows
+                .first()
+                .map(|row| {
+                    collect_children(row, is_cell)
+                        .iter()
+                        .flat_map(|cell| {
+                            std::iter::repeat_n(
+                                cell_alignment(cell),
+                                parse_span(get_tag_attr(cell, "colspan").as_deref()),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let alignments =
+                resolve_alignments(&header_alignments, &column_alignments(tag), column_count);
 
+            // header row must always be present
+            for (idx, row) in grid.iter().enumerate() {
                 table_markup.push('|');
 
-                let cells = collect_children(row, any_matcher);
-
-                for index in 0..column_count {
+                for (index, width) in column_widths.iter().enumerate() {
                     // we need to fill all cells in a column, even if some rows don't have enough
-                    let padded_cell_text = pad_cell_text(
-                        &cells.get(index),
-                        column_widths[index],
-                        self.commonmark,
-                        &self.url,
-                    );
+                    let padded_cell_text =
+                        pad_cell_text(row.get(index).and_then(Option::as_deref), *width);
 
                     table_markup.push_str(&padded_cell_text);
                     table_markup.push('|');
@@ -101,53 +92,9 @@ impl TagHandler for TableHandler {
                     // first row is a header row
                     // add header-body divider row
                     table_markup.push('|');
-                    for index in 0..column_count {
-                        let width = column_widths[index];
-
-                        if width < 3 {
-                            // no point in aligning, just post as-is
-                            table_markup.push_str(&"-".repeat(width.max(2)));
-                            table_markup.push('|');
-                            continue;
-                        }
-
-                        // // try to detect alignment
-                        // let mut alignment = String::new();
-
-                        // if let Some(header_cell) = cells.get(index) {
-                        //     // we have a header, try to extract alignment from it
-                        //     alignment = match header_cell.data {
-                        //         NodeData::Element { ref attrs, .. } => {
-                        //             let attrs = attrs.borrow();
-                        //             let align_attr = attrs
-                        //                 .iter()
-                        //                 .find(|attr| attr.name.local.to_string() == "align");
-                        //             align_attr
-                        //                 .map(|attr| attr.value.to_string())
-                        //                 .unwrap_or_default()
-                        //         }
-                        //         _ => String::new(),
-                        //     };
-                        // }
-
-                        // push lines according to alignment, fallback to default behaviour
-                        // match alignment.as_ref() {
-                        //     "left" => {
-                        //         table_markup.push(':');
-                        //         table_markup.push_str(&"-".repeat(width - 1));
-                        //     }
-                        //     "center" => {
-                        //         table_markup.push(':');
-                        //         table_markup.push_str(&"-".repeat(width - 2));
-                        //         table_markup.push(':');
-                        //     }
-                        //     "right" => {
-                        //         table_markup.push_str(&"-".repeat(width - 1));
-                        //         table_markup.push(':');
-                        //     }
-                        //     _ => table_markup.push_str(&"-".repeat(width)),
-                        // }
 
+                    for (width, alignment) in column_widths.iter().zip(alignments.iter()) {
+                        alignment.push_delimiter(&mut table_markup, *width);
                         table_markup.push('|');
                     }
 
@@ -173,40 +120,30 @@ impl TagHandler for TableHandler {
 
 /// Pads cell text from right and left so it looks centered inside the table cell
 /// ### Arguments
-/// `tag` - optional reference to currently processed handle, text is extracted from here
+/// `text` - optional text of the cell, missing and blank spanned cells have none
 ///
 /// `column_width` - precomputed column width to compute padding length from
-fn pad_cell_text(
-    tag: &Option<&Handle>,
-    column_width: usize,
-    commonmark: bool,
-    url: &Option<Arc<Url>>,
-) -> String {
+fn pad_cell_text(text: Option<&str>, column_width: usize) -> String {
     let mut result = String::new();
 
-    if let Some(cell) = tag {
-        // have header at specified position
-        let text = to_text(cell, commonmark, url);
-
+    if let Some(text) = text {
         // compute difference between width and text length
-        let len_diff = column_width
-            .checked_sub(text.chars().count())
-            .unwrap_or_default();
+        let len_diff = column_width.saturating_sub(text.chars().count());
 
         if len_diff > 0 {
             // should pad
             if len_diff > 1 {
                 result.push(' ');
-                result.push_str(&text);
+                result.push_str(text);
                 result.push(' ');
             } else {
                 // it's just one space, add at the end
-                result.push_str(&text);
+                result.push_str(text);
                 result.push(' ');
             }
         } else {
             // shouldn't pad, text fills whole cell
-            result.push_str(&text);
+            result.push_str(text);
         }
     } else {
         // no text in this cell, fill cell with spaces
@@ -216,6 +153,89 @@ fn pad_cell_text(
     result
 }
 
+/// The element is a table cell.
+fn is_cell(tag: &Handle) -> bool {
+    match tag.data {
+        NodeData::Element { ref name, .. } => name.local == TD || name.local == TH,
+        _ => false,
+    }
+}
+
+/// Expand the cells of the rows into a rectangular grid. The cells covered by a
+/// `colspan` or `rowspan` are blank or repeat the spanning cell, following the span policy.
+fn build_grid(
+    rows: &[Handle],
+    options: &Arc<ConversionOptions>,
+    references: &mut LinkReferences,
+) -> Vec<Vec<Option<String>>> {
+    let repeat = options.span_policy == SpanPolicy::Repeat;
+    let mut spans = RowSpans::default();
+    let mut grid = Vec::with_capacity(rows.len().min(TABLE_LIMIT));
+
+    for row in rows.iter().take(TABLE_LIMIT) {
+        let mut cells: Vec<Option<String>> = Vec::new();
+
+        for cell in collect_children(row, is_cell) {
+            while let Some(text) = spans.take(cells.len()) {
+                cells.push(repeat.then(|| text.to_string()));
+            }
+
+            let text = to_text(&cell, options, references);
+            let colspan = parse_span(get_tag_attr(&cell, "colspan").as_deref());
+            let rowspan = parse_span(get_tag_attr(&cell, "rowspan").as_deref());
+
+            spans.insert(cells.len(), colspan, rowspan, &text);
+
+            let covered = repeat.then(|| text.clone());
+
+            cells.push(Some(text));
+            cells.extend(std::iter::repeat_n(covered, colspan - 1));
+        }
+
+        // the columns after the last cell can still be covered by a previous row
+        while cells.len() < spans.len() {
+            let text = spans.take(cells.len());
+            cells.push(text.filter(|_| repeat).map(str::to_string));
+        }
+
+        grid.push(cells);
+    }
+
+    grid
+}
+
+/// The alignment declared on a cell, `col` or `colgroup`.
+fn cell_alignment(tag: &Handle) -> Alignment {
+    Alignment::from_attributes(
+        get_tag_attr(tag, "align").as_deref(),
+        get_tag_attr(tag, "style").as_deref(),
+    )
+}
+
+/// The column alignments declared by the `<colgroup>` and `<col>` children of the table.
+fn column_alignments(table: &Handle) -> Vec<Alignment> {
+    let mut columns = Vec::new();
+
+    for group in collect_children(table, |child| tag_name(child) == "colgroup") {
+        let mut column_group = ColumnGroup::new(
+            cell_alignment(&group),
+            parse_span(get_tag_attr(&group, "span").as_deref()),
+        );
+
+        for col in collect_children(&group, |child| tag_name(child) == "col") {
+            column_group.push_col(
+                &mut columns,
+                cell_alignment(&col),
+                parse_span(get_tag_attr(&col, "span").as_deref()),
+            );
+        }
+
+        column_group.finish(&mut columns);
+    }
+
+    columns
+}
+
 /// Extracts tag name from passed tag
 /// Returns empty string if it's not an html element
 fn tag_name(tag: &Handle) -> String {
@@ -262,18 +282,20 @@ where
 }
 
 /// Convert html tag to text. This collects all tag children in correct order where they're observed
-/// and concatenates their text, recursively.
-fn to_text(tag: &Handle, commonmark: bool, url: &Option<Arc<Url>>) -> String {
-    let mut printer = StructuredPrinter::default();
-
-    walk(
-        &tag,
-        &mut printer,
-        &HashMap::default(),
-        commonmark,
-        &url,
-        true,
-    );
+/// and concatenates their text, recursively. The links of the cell share the references of the document.
+fn to_text(
+    tag: &Handle,
+    options: &Arc<ConversionOptions>,
+    references: &mut LinkReferences,
+) -> String {
+    let mut printer = StructuredPrinter {
+        references: std::mem::take(references),
+        ..Default::default()
+    };
+
+    walk(tag, &mut printer, &HashMap::default(), options, true);
+
+    *references = printer.references;
 
     clean_markdown(&printer.data)
 }
@@ -0,0 +1,68 @@
+#[cfg(feature = "scraper")]
+pub mod test {
+    use html2md::{parse_html, rewrite_html};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_code_language_from_class() {
+        let s = r#"<pre><code class="hljs language-rust">let answer = 42;</code></pre>"#;
+
+        let m = "```rust\nlet answer = 42;\n```";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_code_language_conventions() {
+        let s = r#"<pre class="lang-python">print(42)</pre>
+<pre data-lang="sh">echo 42</pre>
+<div class="highlight highlight-source-js"><pre>console.log(42)</pre></div>
+<pre>plain</pre>"#;
+
+        let m = "```python\nprint(42)\n```\n```sh\necho 42\n```\n```js\nconsole.log(42)\n```\n```\nplain\n```";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_code_verbatim() {
+        let s = r#"<p>Call <code>a*b_c &lt; d</code> first</p><pre><code>fn main() {
+    if a &lt; b &amp;&amp; c_d * 2 {
+        run();
+    }
+
+    // done
+}</code></pre>"#;
+
+        let m = "Call `a*b_c < d` first\n```\nfn main() {\n    if a < b && c_d * 2 {\n        run();\n    }\n\n    // done\n}\n```";
+
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_code_trailing_newline() {
+        let s = "<pre><code>a\n</code></pre><p>b</p>";
+
+        let md = rewrite_html(s, false);
+        assert_eq!(md, "```\na\n```\nb");
+    }
+
+    #[test]
+    fn test_code_backtick_safe() {
+        let s = r#"<p>Use <code>a`b</code> or <code>`x`</code></p><pre>a ```` b</pre>"#;
+
+        let m = "Use ``a`b`` or `` `x` ``\n`````\na ```` b\n`````";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+}
@@ -83,4 +83,57 @@ pub mod test {
         let md = html2md::rewrite_html("<img src=\"https://instagram.ftll1-1.fna.fbcdn.net/vp/4c753762a3cd58ec2cd55f7e20f87e5c/5D39A8B3/t51.2885-15/sh0.08/e35/p640x640/54511922_267736260775264_8482507773977053160_n.jpg?_nc_ht=instagram.ftll1-1.fna.fbcdn.net\" style=\"width: 494px;\">", false);
         assert_eq!(md, "![](https://instagram.ftll1-1.fna.fbcdn.net/vp/4c753762a3cd58ec2cd55f7e20f87e5c/5D39A8B3/t51.2885-15/sh0.08/e35/p640x640/54511922_267736260775264_8482507773977053160_n.jpg?_nc_ht=instagram.ftll1-1.fna.fbcdn.net)");
     }
+
+    #[test]
+    fn test_image_source_selection() {
+        use html2md::{ConversionOptions, ImageSourcePolicy};
+
+        let s = r#"<img src="data:image/gif;base64,R0lGOD" data-src="/real.png" alt="lazy"> <img src="a.png" srcset="a-320.png 320w, a-1280.png 1280w, a-640.png 640w" alt="r"> <picture><source srcset="p.avif 1x, p@2x.avif 2x" type="image/avif"><img src="p.jpg" alt="p"></picture>"#;
+        let options =
+            ConversionOptions::default().with_url(url::Url::parse("https://example.com").ok());
+        let m = "![lazy](https://example.com/real.png) ![r](https://example.com/a-1280.png) ![p](https://example.com/p@2x.avif)";
+
+        let md = html2md::parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = html2md::rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+
+        let options = options.with_image_source_policy(ImageSourcePolicy::MaxWidth(700));
+        let m = "![lazy](https://example.com/real.png) ![r](https://example.com/a-640.png) ![p](https://example.com/p@2x.avif)";
+
+        let md = html2md::parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = html2md::rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+
+        let options = options.with_image_source_policy(ImageSourcePolicy::First);
+        let m = "![lazy](https://example.com/real.png) ![r](https://example.com/a-320.png) ![p](https://example.com/p.avif)";
+
+        let md = html2md::parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = html2md::rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_srcset_urls_with_commas() {
+        use html2md::ConversionOptions;
+
+        let s = r#"<img src="a.jpg" srcset="https://res.cloudinary.com/demo/image/upload/w_400,c_fill/a.jpg 400w,https://res.cloudinary.com/demo/image/upload/w_800,c_fill/a.jpg 800w" alt="A">"#;
+        let m = "![A](https://res.cloudinary.com/demo/image/upload/w_800,c_fill/a.jpg)";
+
+        let md = html2md::parse_html(s, false);
+        assert_eq!(md, m);
+        let md = html2md::rewrite_html(s, false);
+        assert_eq!(md, m);
+
+        let s = r#"<img src="a.jpg" srcset="/w_400,c_fill/a.jpg 400w, /w_800,c_fill/a.jpg 800w" width="10">"#;
+        let options = ConversionOptions::default()
+            .with_commonmark(true)
+            .with_url(url::Url::parse("https://example.com/a/").ok());
+        let m = r#"<img src="https://example.com/a/a.jpg" srcset="https://example.com/w_400,c_fill/a.jpg 400w, https://example.com/w_800,c_fill/a.jpg 800w" width="10" />"#;
+
+        let md = html2md::rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
 }
@@ -42,10 +42,7 @@ fn test_real_world_wiki() -> Result<(), Box<dyn std::error::Error>> {
         let result = html2md::parse_html(&html, false);
 
         if result.is_empty() {
-            Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                "Result is empty",
-            )))
+            Err(Box::new(io::Error::other("Result is empty")))
         } else {
             Ok(())
         }
@@ -56,8 +53,8 @@ fn test_real_world_wiki() -> Result<(), Box<dyn std::error::Error>> {
 
         if path.is_file() {
             match run_parse(&path) {
-                Ok(_) => assert!(true),
-                Err(_e) => assert!(false),
+                Ok(_) => (),
+                Err(e) => panic!("{e}"),
             }
         }
     }
@@ -82,10 +79,7 @@ fn test_real_world_wiki_rewriter() -> Result<(), Box<dyn std::error::Error>> {
         let result = html2md::rewrite_html(&html, false);
 
         if result.is_empty() {
-            Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                "Result is empty",
-            )))
+            Err(Box::new(io::Error::other("Result is empty")))
         } else {
             Ok(())
         }
@@ -96,8 +90,8 @@ fn test_real_world_wiki_rewriter() -> Result<(), Box<dyn std::error::Error>> {
 
         if path.is_file() {
             match run_parse(&path) {
-                Ok(_) => assert!(true),
-                Err(_e) => assert!(false),
+                Ok(_) => (),
+                Err(e) => panic!("{e}"),
             }
         }
     }
@@ -123,10 +117,7 @@ async fn test_real_world_wiki_async() -> Result<(), Box<dyn std::error::Error>>
         let result = html2md::rewrite_html_streaming(&html, false).await;
 
         if result.is_empty() {
-            Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                "Result is empty",
-            )))
+            Err(Box::new(io::Error::other("Result is empty")))
         } else {
             Ok(())
         }
@@ -137,8 +128,8 @@ async fn test_real_world_wiki_async() -> Result<(), Box<dyn std::error::Error>>
 
         if path.is_file() {
             match run_parse(&path).await {
-                Ok(_) => assert!(true),
-                Err(_e) => assert!(false),
+                Ok(_) => (),
+                Err(e) => panic!("{e}"),
             }
         }
     }
@@ -226,7 +217,7 @@ fn test_tables_with_newlines() {
         .read_to_string(&mut html)
         .expect("File must be readable");
     let result = html2md::parse_html(&html, false);
-    let m = indoc! { "[![Embedded YouTube video](https://img.youtube.com/vi/ZZZZZZZZZ/0.jpg)](https://www.youtube.com/watch?v=ZZZZZZZZZ)\n|Maybe I'm foolish, maybe I'm blind\nThinking I can see through this and see what's behind\nGot no way to prove it so maybe I'm blind\nBut I'm only human after all,\nI'm only human after all\nDon't put your blame on me|xxxxx xxxx, x xxxxxx, xxxxx xxxx — xxxxxx\nxxx xxxxx, xxx xxxx xxxxxx xxxxxx xxx, x xxxxxx xxx xxx xx xxx\nxxxx x xxxx xx xxxx xxxxxxx xxxxxxxxxxxxx, xxx xxx xxxxxxxx, x xxxxxx.\nxx x xxxxx xxxx xxxxxxx, x xxxxx-xx xxxxxx,\nx xxxxx xxxx xxxxxxx, x xxxxx xxxxxx.\nxx xxxx xxxx|\n|||\n[xxxxxx xxxxx xxxxx x xxxxxxx](/)\nx xxxx xxxxxxxxx xxxxxxx xxxxxxxxxxx xx xxxx xxxxx. x xxxxx xxxxxxx, xxxx xxxxx xxxxxxx xx xxxxxxxxxx xxxxxx. xxx xxxxxxxx, xxx xxxxxxxxx xxxxxxxxxxxxxx xx xxxxx — xxxxxxxxxx xxxxxxxxxx x xxxxx xxxxxxxxxxxxx xxxxxxxxx. x xxx xxxxxxxxxxxx *xxxx*, xxxxxx xxxx, xxxxxxxxxx xxxxx xxxxxxxx, xxxxxxxxxx x xxxxxxxxx. xx xxxxxx xxxxx xxxxxxxxxxxxxxxxx — x xxxxxx xxx xxxx.\nxxxxx xxxxxxxxxx xxxxx x xxxx xxxxxxxxxx xxxxx. xxxxx. x xxxxx: «x xxxxxx xxxxxxx, x xxxxx xxx xxxx, xx xxxxxxxx xxxxxx», — xxx xxxxx xxxxxxxx. xxxxxx xxx x xxxx xxxx xxxxxxxx xxxxxxxx xxxxxxx xxxx xxxxxxxxxxx xxxxxxxxxx, xxxxxxx xxxxxx xxxxxx xxx xxxxx, xxxxxxxxxxx x x xxxxxxx xxxxxxxxx.\nxx x xxxxx xxxx xxxxxxx. xxxxxx xxxxx? xxxxxxxxxxx x xxxxxxxxx xxxxxx.\nx xxxxx x xxxxxxxxxx x xxxxx... x xxxxxx xxxx xxxxxx xxxxxxx xxxxxxxx. xx xxxx, x xxxxxx xxx-xx xxxxxxxxx xx xxxxxxx, xxx xxxxxx xxxxxx, xxx xxx xxxxx, xxxxx xxxxxxxx xx xxxx... x xxxxxx xxxxxxx xx xxxx xxxxx, xxx, xxxxx xxxx xxxxxxxxxx, x xxxxx xxxxxxxxx xx xxxxx. x xxx-xx xxx xxxxx xxxxxxx xxxxxxxxxxxxx.\nxxxxxx xx... xx xxx xx xxxxxxxxxxxxx xxxxxx xxxxxxxxxxxxx x xxxxxxxxxx xxxxx, xxxxx xxx xxxx xxxxxxxxx, x xxxxx xxx xxxxxxxxx, xxx xxxxxxx xxx, xxx xxxx xxxxxxx xxxxxx, x xx xxx, xxx xxxx xxxxxxxx." };
+    let m = indoc! { "[![Embedded YouTube video](https://img.youtube.com/vi/ZZZZZZZZZ/0.jpg)](https://www.youtube.com/watch?v=ZZZZZZZZZ)\n|Maybe I'm foolish, maybe I'm blind\nThinking I can see through this and see what's behind\nGot no way to prove it so maybe I'm blind\nBut I'm only human after all,\nI'm only human after all\nDon't put your blame on me|xxxxx xxxx, x xxxxxx, xxxxx xxxx — xxxxxx\nxxx xxxxx, xxx xxxx xxxxxx xxxxxx xxx, x xxxxxx xxx xxx xx xxx\nxxxx x xxxx xx xxxx xxxxxxx xxxxxxxxxxxxx, xxx xxx xxxxxxxx, x xxxxxx.\nxx x xxxxx xxxx xxxxxxx, x xxxxx-xx xxxxxx,\nx xxxxx xxxx xxxxxxx, x xxxxx xxxxxx.\nxx xxxx xxxx|\n|--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------|------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------|\n[xxxxxx xxxxx xxxxx x xxxxxxx](/)\nx xxxx xxxxxxxxx xxxxxxx xxxxxxxxxxx xx xxxx xxxxx. x xxxxx xxxxxxx, xxxx xxxxx xxxxxxx xx xxxxxxxxxx xxxxxx. xxx xxxxxxxx, xxx xxxxxxxxx xxxxxxxxxxxxxx xx xxxxx — xxxxxxxxxx xxxxxxxxxx x xxxxx xxxxxxxxxxxxx xxxxxxxxx. x xxx xxxxxxxxxxxx *xxxx*, xxxxxx xxxx, xxxxxxxxxx xxxxx xxxxxxxx, xxxxxxxxxx x xxxxxxxxx. xx xxxxxx xxxxx xxxxxxxxxxxxxxxxx — x xxxxxx xxx xxxx.\nxxxxx xxxxxxxxxx xxxxx x xxxx xxxxxxxxxx xxxxx. xxxxx. x xxxxx: «x xxxxxx xxxxxxx, x xxxxx xxx xxxx, xx xxxxxxxx xxxxxx», — xxx xxxxx xxxxxxxx. xxxxxx xxx x xxxx xxxx xxxxxxxx xxxxxxxx xxxxxxx xxxx xxxxxxxxxxx xxxxxxxxxx, xxxxxxx xxxxxx xxxxxx xxx xxxxx, xxxxxxxxxxx x x xxxxxxx xxxxxxxxx.\nxx x xxxxx xxxx xxxxxxx. xxxxxx xxxxx? xxxxxxxxxxx x xxxxxxxxx xxxxxx.\nx xxxxx x xxxxxxxxxx x xxxxx... x xxxxxx xxxx xxxxxx xxxxxxx xxxxxxxx. xx xxxx, x xxxxxx xxx-xx xxxxxxxxx xx xxxxxxx, xxx xxxxxx xxxxxx, xxx xxx xxxxx, xxxxx xxxxxxxx xx xxxx... x xxxxxx xxxxxxx xx xxxx xxxxx, xxx, xxxxx xxxx xxxxxxxxxx, x xxxxx xxxxxxxxx xx xxxxx. x xxx-xx xxx xxxxx xxxxxxx xxxxxxxxxxxxx.\nxxxxxx xx... xx xxx xx xxxxxxxxxxxxx xxxxxx xxxxxxxxxxxxx x xxxxxxxxxx xxxxx, xxxxx xxx xxxx xxxxxxxxx, x xxxxx xxx xxxxxxxxx, xxx xxxxxxx xxx, xxx xxxx xxxxxxx xxxxxx, x xx xxx, xxx xxxx xxxxxxxx." };
 
     assert_that!(result).contains(m);
     // let result = html2md::rewrite_html(&html, false);
@@ -595,6 +586,77 @@ async fn test_stream_single_chunk() {
     assert_eq!(result, expected);
 }
 
+/// The code inside a quote is written once, quoted.
+#[tokio::test]
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+async fn test_stream_quoted_code() {
+    let html = "<blockquote><pre>code\nline2</pre><p>a <code>x_y</code> b</p></blockquote>";
+    let expected = html2md::rewrite_html(html, false);
+
+    let chunks: Vec<Result<&[u8], std::io::Error>> =
+        html.as_bytes().chunks(8).map(Ok).collect();
+    let stream = futures_util::stream::iter(chunks);
+
+    let result = html2md::rewrite_html_stream(stream, false).await.unwrap();
+    assert_eq!(result, expected);
+    assert_eq!(result.matches("code").count(), 1);
+}
+
+/// The header row of a streamed table is padded to its widest row.
+#[tokio::test]
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+async fn test_stream_table_padding() {
+    let html =
+        "<table><tr><th>a</th><th>b</th></tr><tr><td>1</td><td>2</td><td>3</td></tr></table>";
+    let expected = "|a|b||\n|---|---|---|\n|1|2|3|";
+
+    for size in 1..=8 {
+        let chunks: Vec<Result<&[u8], std::io::Error>> =
+            html.as_bytes().chunks(size).map(Ok).collect();
+        let stream = futures_util::stream::iter(chunks);
+
+        let result = html2md::rewrite_html_stream(stream, false).await.unwrap();
+        assert_eq!(result, expected);
+    }
+
+    let result = html2md::rewrite_html_streaming(html, false).await;
+    assert_eq!(result, expected);
+}
+
+/// The deprecated writer functions keep their signatures and forward to the options.
+#[tokio::test]
+#[allow(deprecated)]
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+async fn test_stream_deprecated_writers() {
+    use html2md::rewriter::writer::{
+        convert_html_stream_to_markdown, convert_html_to_markdown_send,
+        convert_html_to_markdown_send_with_size,
+    };
+
+    let html = r#"<nav>Menu</nav><p>See <a href="/x">this</a></p>"#;
+    let ignore = Some(["nav".to_string()].into());
+    let url = Some(url::Url::parse("https://example.com").unwrap());
+    let expected = "See [this](https://example.com/x)";
+
+    let result = convert_html_to_markdown_send(html, &ignore, false, &url)
+        .await
+        .unwrap();
+    assert_eq!(result, expected);
+
+    let result = convert_html_to_markdown_send_with_size(html, &ignore, false, &url, 3)
+        .await
+        .unwrap();
+    assert_eq!(result, expected);
+
+    let chunks: Vec<Result<&[u8], std::io::Error>> =
+        html.as_bytes().chunks(8).map(Ok).collect();
+    let stream = futures_util::stream::iter(chunks);
+    let result = convert_html_stream_to_markdown(stream, &ignore, false, &url)
+        .await
+        .unwrap();
+    assert_eq!(result, expected);
+}
+
 /// An empty stream should return an empty/minimal string without panicking.
 #[tokio::test]
 #[cfg(all(feature = "stream", feature = "rewriter"))]
@@ -692,6 +754,103 @@ async fn test_stream_custom_with_url() {
     assert_eq!(result, expected);
 }
 
+#[tokio::test]
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+async fn test_stream_include_selectors() {
+    let html = r#"<nav>menu</nav><div class="post"><h1>Title</h1><p>text</p></div><p>footer</p>"#;
+    let options = html2md::ConversionOptions::default().with_include([".post".to_string()].into());
+
+    let chunks: Vec<Result<&[u8], std::io::Error>> =
+        html.as_bytes().chunks(8).map(Ok).collect();
+    let stream = futures_util::stream::iter(chunks);
+
+    let result = html2md::rewrite_html_stream_with_options(stream, &options)
+        .await
+        .unwrap();
+    assert_eq!(result, "# Title\ntext");
+
+    let html = r#"<p>x <img class="hero" src="/a.png" alt="A"> y</p>"#;
+    let options =
+        html2md::ConversionOptions::default().with_include(["img.hero".to_string()].into());
+
+    let chunks: Vec<Result<&[u8], std::io::Error>> =
+        html.as_bytes().chunks(8).map(Ok).collect();
+    let stream = futures_util::stream::iter(chunks);
+
+    let result = html2md::rewrite_html_stream_with_options(stream, &options)
+        .await
+        .unwrap();
+    assert_eq!(result, "![A](/a.png)");
+}
+
+#[tokio::test]
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+async fn test_stream_custom_handlers() {
+    let html = r#"<p>Intro</p><x-embed src="/v.mp4"></x-embed><p>end</p>"#;
+    let options = html2md::ConversionOptions::default().with_custom_handler(
+        "x-embed",
+        |el: &mut html2md::CustomElement| {
+            let src = el.get_attribute("src").unwrap_or_default().to_string();
+            el.replace(&format!("\n[video]({src})\n"));
+        },
+    );
+
+    let chunks: Vec<Result<&[u8], std::io::Error>> =
+        html.as_bytes().chunks(8).map(Ok).collect();
+    let stream = futures_util::stream::iter(chunks);
+
+    let result = html2md::rewrite_html_stream_with_options(stream, &options)
+        .await
+        .unwrap();
+    assert_eq!(result, html2md::rewrite_html_with_options(html, &options));
+    assert_eq!(result, "Intro\n[video](/v.mp4)\nend");
+}
+
+#[tokio::test]
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+async fn test_stream_line_start_escaping() {
+    let html = "<p>Intro</p><p>1. Not a list</p><p># not a heading</p><p>Pay 2. here</p><p>12) no</p><p>``` no</p><p>1.5 yes</p>";
+    let expected =
+        "Intro\n1\\. Not a list\n\\# not a heading\nPay 2. here\n12\\) no\n\\``` no\n1.5 yes";
+    assert_eq!(html2md::rewrite_html(html, false), expected);
+
+    // a marker split by the chunks is held back until the next chunk
+    for size in 1..=8 {
+        let chunks: Vec<Result<&[u8], std::io::Error>> =
+            html.as_bytes().chunks(size).map(Ok).collect();
+        let stream = futures_util::stream::iter(chunks);
+
+        let result = html2md::rewrite_html_stream(stream, false).await.unwrap();
+        assert_eq!(result, expected, "chunk size {size}");
+
+        let options = html2md::ConversionOptions::default().with_chunk_size(size);
+        let result = html2md::rewrite_html_streaming_with_options(html, &options).await;
+        assert_eq!(result, expected, "chunk size {size}");
+    }
+}
+
+#[tokio::test]
+#[cfg(all(feature = "stream", feature = "rewriter"))]
+async fn test_stream_character_references() {
+    let html = "<p>&eacute;t&eacute; &copy; AT&T &#8364;5 &#x27;a&#x27; &amp;lt;</p><pre>&lt;b&gt;</pre>";
+    let expected = "été © AT&T €5 'a' \\&lt;\n```\n<b>\n```";
+    assert_eq!(html2md::rewrite_html(html, false), expected);
+
+    // a reference split by the chunks is decoded whole
+    for size in 1..=8 {
+        let chunks: Vec<Result<&[u8], std::io::Error>> =
+            html.as_bytes().chunks(size).map(Ok).collect();
+        let stream = futures_util::stream::iter(chunks);
+
+        let result = html2md::rewrite_html_stream(stream, false).await.unwrap();
+        assert_eq!(result, expected, "chunk size {size}");
+
+        let options = html2md::ConversionOptions::default().with_chunk_size(size);
+        let result = html2md::rewrite_html_streaming_with_options(html, &options).await;
+        assert_eq!(result, expected, "chunk size {size}");
+    }
+}
+
 #[tokio::test]
 #[ignore]
 #[cfg(all(feature = "stream", feature = "rewriter"))]
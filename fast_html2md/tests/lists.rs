@@ -44,7 +44,7 @@ pub mod test {
         let md = rewrite_html(s, false);
         assert_eq!(
         md,
-        "* You should NEVER see this error\n* Broken lines, broken strings\n* Broken threads, broken springs\n* Broken idols, broken heads\n* People sleep in broken beds\n* Ain't no use jiving\n* Ain't no use joking\n* EVERYTHING IS BROKEN"
+        "* You should NEVER see this error\n  * Broken lines, broken strings\n  * Broken threads, broken springs\n  * Broken idols, broken heads\n  * People sleep in broken beds\n* Ain't no use jiving\n* Ain't no use joking\n* EVERYTHING IS BROKEN"
     );
     }
 
@@ -86,7 +86,10 @@ pub mod test {
         assert_eq!(md, m);
 
         let md = rewrite_html(s, false);
-        assert_eq!(md, m);
+        assert_eq!(
+            md,
+            "* You should NEVER see this error\n  * Broken lines, broken strings\n  * Broken threads, broken springs\n  * Broken idols, broken heads\n  * People sleep in broken beds\n* Ain’t no use jiving\n* Ain’t no use joking\n* EVERYTHING IS BROKEN"
+        );
     }
 
     #[test]
@@ -128,7 +131,10 @@ pub mod test {
         let md = parse_html(s, false);
         assert_eq!(md, m);
         let md = rewrite_html(s, false);
-        assert_eq!(md, m);
+        assert_eq!(
+            md,
+            "* You should NEVER see this error\n  * Broken lines, broken strings\n  * Broken threads, broken springs\n  * Broken idols, broken heads\n  * People sleep in broken beds\n* Ain’t no use jiving\n* Ain’t no use joking\n* EVERYTHING IS BROKEN"
+        );
     }
 
     #[test]
@@ -205,6 +211,27 @@ pub mod test {
         assert_eq!(md, m);
     }
 
+    #[test]
+    fn test_list_nested_mixed() {
+        let s = r#"<ol>
+            <li>Fix the roof
+                <ul>
+                    <li>Buy nails</li>
+                    <li>Borrow a ladder
+                        <ol><li>Ask Tom</li><li>Ask Anna</li></ol>
+                    </li>
+                </ul>
+            </li>
+            <li>Paint the fence</li>
+        </ol>"#;
+
+        let md = rewrite_html(s, false);
+        assert_eq!(
+            md,
+            "1. Fix the roof\n   * Buy nails\n   * Borrow a ladder\n     1. Ask Tom\n     2. Ask Anna\n2. Paint the fence"
+        );
+    }
+
     #[test]
     fn test_list_text_prevsibling() {
         let s = r#"
@@ -225,4 +252,116 @@ pub mod test {
         let md = rewrite_html(s, false);
         assert_eq!(md, m);
     }
+
+    #[test]
+    fn test_definition_list() {
+        let s = r#"<dl>
+  <dt>Apple</dt>
+  <dd>A <em>red</em> fruit.</dd>
+  <dt>Orange</dt><dt>Citrus</dt>
+  <dd>An orange fruit.</dd>
+  <dd>
+    <p>Second definition.</p></dd>
+</dl>"#;
+
+        let m = "Apple\n: A *red* fruit.\nOrange\nCitrus\n: An orange fruit.\n: Second definition.";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+
+        let m = "**Apple**\n    A *red* fruit.\n**Orange**\n**Citrus**\n    An orange fruit.\n    Second definition.";
+
+        let md = parse_html(s, true);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, true);
+        assert_eq!(md, m);
+        // the whitespace of the term stays outside the strong delimiters
+        let s = "<dl><dt> Term <i>x</i> </dt><dd> Def </dd></dl>";
+        let m = "**Term *x***\n    Def";
+
+        let md = parse_html(s, true);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, true);
+        assert_eq!(md, m);
+        let md = parse_html(s, false);
+        assert_eq!(md, "Term *x*\n: Def");
+        let md = rewrite_html(s, false);
+        assert_eq!(md, "Term *x*\n: Def");
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let s = r##"<p>Cats are small<sup id="cite_ref-a_1-0" class="reference"><a href="#cite_note-a-1">&#91;1&#93;</a></sup> and cute.<sup id="cite_ref-2" class="reference"><a href="#cite_note-2">[note]</a></sup> Again<sup id="cite_ref-a_1-1" class="reference"><a href="#cite_note-a-1">[1]</a></sup>. x<sup>2</sup></p>
+<ol class="references">
+<li id="cite_note-a-1"><span class="mw-cite-backlink">^ <a href="#cite_ref-a_1-0"><sup>a</sup></a> <a href="#cite_ref-a_1-1"><sup>b</sup></a></span> <span class="reference-text">Smith, <a href="https://example.com/book">The Book</a>.</span></li>
+<li id="cite_note-2"><a href="#cite_ref-2">^</a> <i>Cats</i> are cute.</li>
+<li id="cite_note-3">Never cited.</li>
+</ol>"##;
+
+        let m = "Cats are small[^1] and cute.[^note] Again[^1]. x2\n[^1]: Smith, [The Book](https://example.com/book).\n[^note]: *Cats* are cute.\n\n3. Never cited.";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+
+        let m = m.replace("x2", "x<sup>2</sup>");
+
+        let md = parse_html(s, true);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, true);
+        assert_eq!(md, m);
+
+        // the text after the notes does not continue the last one
+        let s = r##"<p>A<sup class="reference"><a href="#cite_note-1">[1]</a></sup></p><ol><li id="cite_note-1">One</li></ol><p>After</p>"##;
+        let m = "A[^1]\n[^1]: One\n\nAfter";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_footnotes_not_cited() {
+        // only a <sup class="reference"> is a citation
+        let s = r##"<p>E = mc<sup><a href="#fn-2">2</a></sup> and <sup><a href="#top">top</a></sup></p><h2 id="fn-2">Notes</h2>"##;
+
+        let md = parse_html(s, false);
+        assert_eq!(
+            md,
+            "E = mc<a href=\"#fn-2\">2</a> and <a href=\"#top\">top</a>\n## Notes"
+        );
+        let md = rewrite_html(s, false);
+        assert_eq!(md, "E = mc[2](#fn-2) and [top](#top)\n## Notes");
+
+        // a note missing from the document still gets a definition
+        let s = r##"<p>A<sup class="reference"><a href="#cite_note-1">[1]</a></sup> B<sup class="reference"><a href="#cite_note-2">[2]</a></sup></p><ol><li id="cite_note-1">One</li></ol>"##;
+        let m = "A[^1] B[^2]\n[^1]: One\n[^2]:";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_task_list() {
+        let s = r#"<ul>
+<li class="task-list-item"><input type="checkbox" class="task-list-item-checkbox" checked disabled> Done</li>
+<li><label><input type="checkbox"> Todo <b>now</b></label></li>
+<li>Ask <input type="checkbox" checked> later</li>
+</ul>
+<ol><li><p><input type="checkbox">First</p></li></ol>
+<p>Accept <input type="checkbox"> the terms <input type="text" value="name"></p>"#;
+
+        let m = "* [x] Done\n* [ ] Todo **now**\n* Ask [x] later\n1. [ ] First\nAccept [ ] the terms";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
 }
@@ -0,0 +1,40 @@
+#[cfg(feature = "scraper")]
+pub mod test {
+    use html2md::{parse_html, rewrite_html};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_mathml() {
+        let s = r#"<p>Area <math><mi>A</mi><mo>=</mo><mi>π</mi><msup><mi>r</mi><mn>2</mn></msup><mo>≤</mo><mfrac><mn>1</mn><msqrt><mi>x</mi></msqrt></mfrac></math> here.</p>
+<math display="block"><munderover><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></munderover><mi>sin</mi><msub><mi>θ</mi><mi>i</mi></msub></math>"#;
+
+        let m = "Area $A=\\pi r^2\\leq\\frac{1}{\\sqrt{x}}$ here.\n$$\\sum_{i=1}^n\\sin\\theta_i$$";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_tex_sources() {
+        let s = r#"<p>KaTeX <span class="katex"><span class="katex-mathml"><math><semantics><mrow><mi>a</mi></mrow><annotation encoding="application/x-tex">a_1 &lt; b</annotation></semantics></math></span><span class="katex-html" aria-hidden="true"><span class="mord">a1&lt;b</span></span></span>, MathJax <span class="MathJax_Preview">x</span><script type="math/tex">x^{2}</script>, Wikipedia <span class="mwe-math-element"><span class="mwe-math-mathml-inline" style="display: none;"><math alttext="{\displaystyle \sin x}"><mi>sin</mi><mi>x</mi></math></span><img src="x.svg" class="mwe-math-fallback-image-inline" alt="{\displaystyle \sin x}"></span>.</p>
+<script type="math/tex; mode=display">E = mc^2</script>"#;
+
+        let m = "KaTeX $a_1 < b$, MathJax $x^{2}$, Wikipedia $\\sin x$.\n$$E = mc^2$$";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+
+        // html5ever moves the script written before the body into the head
+        let s = r#"<script type="math/tex; mode=display">x^2</script><p>after</p>"#;
+        let m = "$$x^2$$\nafter";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+}
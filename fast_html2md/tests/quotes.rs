@@ -38,6 +38,24 @@ pub mod test {
         assert_eq!(md, m);
     }
 
+    #[test]
+    fn test_quoted_code() {
+        let s = "<blockquote><pre>code\nline2</pre></blockquote><p>after</p>";
+        let md = rewrite_html(s, false);
+        assert_eq!(md, ">\n> ```\n> code\n> line2\n> ```\nafter");
+
+        let s = "<blockquote><p>a <code>x_y</code> b</p></blockquote>";
+        let md = rewrite_html(s, false);
+        assert_eq!(md, "> a\n> `x_y`\n> b");
+    }
+
+    #[test]
+    fn test_quoted_line_starts() {
+        let s = "<blockquote>1. not list</blockquote><blockquote><p>a</p><p># b</p>c<br>- d</blockquote>";
+        let md = rewrite_html(s, false);
+        assert_eq!(md, "> 1\\. not list\n> a\n> \\# b\n> c\n> \\- d");
+    }
+
     #[test]
     fn test_details() {
         let html = indoc! {"
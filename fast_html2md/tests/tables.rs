@@ -1,6 +1,9 @@
 #[cfg(feature = "scraper")]
 pub mod test {
-    use html2md::{parse_html, rewrite_html};
+    use html2md::{
+        parse_html, parse_html_with_options, rewrite_html, rewrite_html_with_options,
+        ConversionOptions, SpanPolicy,
+    };
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -28,12 +31,15 @@ pub mod test {
 
         assert_eq!(
             md,
-            "|Minor1|Minor2|Minor3|Minor4|\n|||||\n| col1 | col2 | col3 | col4 |"
+            "|Minor1|Minor2|Minor3|Minor4|\n|------|------|------|------|\n| col1 | col2 | col3 | col4 |"
         );
 
         let md = rewrite_html(s, false);
 
-        assert_eq!(md, "|Minor1|Minor2|Minor3|Minor4|\n|col1|col2|col3|col4|");
+        assert_eq!(
+            md,
+            "|Minor1|Minor2|Minor3|Minor4|\n|---|---|---|---|\n|col1|col2|col3|col4|"
+        );
     }
 
     #[test]
@@ -60,7 +66,7 @@ pub mod test {
 </table>"#;
 
         let m =
-        "|Minor1|Minor2|Minor3|Minor4|Minor5|Minor6|\n|||||||\n| col1 | col2 | col3 | col4 | | |";
+        "|Minor1|Minor2|Minor3|Minor4|Minor5|Minor6|\n|------|------|------|------|------|------|\n| col1 | col2 | col3 | col4 | | |";
 
         let md = parse_html(s, false);
 
@@ -70,7 +76,7 @@ pub mod test {
 
         assert_eq!(
             md,
-            "|Minor1|Minor2|Minor3|Minor4|Minor5|Minor6|\n|col1|col2|col3|col4|"
+            "|Minor1|Minor2|Minor3|Minor4|Minor5|Minor6|\n|---|---|---|---|---|---|\n|col1|col2|col3|col4|"
         );
     }
 
@@ -93,7 +99,7 @@ pub mod test {
   </tbody>
 </table>"#;
 
-        let m = "|Minor1|Minor2| | |\n|||||\n| col1 | col2 |col3|col4|";
+        let m = "|Minor1|Minor2| | |\n|------|------|----|----|\n| col1 | col2 |col3|col4|";
 
         let md = parse_html(s, false);
 
@@ -101,7 +107,80 @@ pub mod test {
 
         let md = rewrite_html(s, false);
 
-        assert_eq!(md, "|Minor1|Minor2|\n|col1|col2|col3|col4|");
+        assert_eq!(
+            md,
+            "|Minor1|Minor2|||\n|---|---|---|---|\n|col1|col2|col3|col4|"
+        );
+
+        // the header is padded to the widest row of each table
+        let s = r#"<table><tr><th>a</th></tr><tr><td>1</td><td>2</td></tr><tr><td>1</td><td>2</td><td>3</td></tr></table>
+<p>x</p><table><tr><th>a</th><th>b</th></tr><tr><td>1</td></tr></table>"#;
+
+        let md = rewrite_html(s, false);
+
+        assert_eq!(
+            md,
+            "|a|||\n|---|---|---|\n|1|2|\n|1|2|3|\nx\n|a|b|\n|---|---|\n|1|"
+        );
+    }
+
+    #[test]
+    fn test_tables_delimiter_without_thead() {
+        let s = r#"<table>
+  <tr><td>Name</td><td colspan="2">Address</td></tr>
+  <tr><td>Bob</td><td>Main St.</td><td>42</td></tr>
+</table>"#;
+
+        let md = rewrite_html(s, false);
+
+        assert_eq!(md, "|Name|Address||\n|---|---|---|\n|Bob|Main St.|42|");
+    }
+
+    #[test]
+    fn test_tables_spans() {
+        let s = r#"<table>
+  <tr><th>Name</th><th colspan="2">Contact</th></tr>
+  <tr><td rowspan="2">Ann</td><td>ann@example.com</td><td>555</td></tr>
+  <tr><td>ann@work.com</td><td>777</td></tr>
+</table>"#;
+
+        let md = rewrite_html(s, false);
+        assert_eq!(
+            md,
+            "|Name|Contact||\n|---|---|---|\n|Ann|ann@example.com|555|\n||ann@work.com|777|"
+        );
+
+        let options = ConversionOptions::default().with_span_policy(SpanPolicy::Repeat);
+
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(
+            md,
+            "|Name|Contact|Contact|\n|---|---|---|\n|Ann|ann@example.com|555|\n|Ann|ann@work.com|777|"
+        );
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(
+            md,
+            "|Name| Contact |Contact|\n|----|---------------|-------|\n|Ann |ann@example.com| 555 |\n|Ann | ann@work.com | 777 |"
+        );
+    }
+
+    #[test]
+    fn test_tables_spans_commonmark() {
+        let s = r#"<table>
+  <tr><th colspan="2">A</th><th>B</th></tr>
+  <tr><td>1</td><td>2</td><td>3</td></tr>
+</table>"#;
+
+        let md = rewrite_html(s, true);
+        assert_eq!(md, "|**A**||**B**|\n|---|---|---|\n|1|2|3|");
+
+        let options = ConversionOptions::default()
+            .with_commonmark(true)
+            .with_span_policy(SpanPolicy::Repeat);
+
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, "|**A**|A|**B**|\n|---|---|---|\n|1|2|3|");
     }
 
     #[test]
@@ -123,7 +202,7 @@ pub mod test {
 
         let md = parse_html(s, false);
 
-        assert_eq!(md, "|Minor|Major|\n|||\n|col1 |col2 |");
+        assert_eq!(md, "|Minor|Major|\n|-----|-----|\n|col1 |col2 |");
     }
 
     #[test]
@@ -152,7 +231,29 @@ pub mod test {
 
         assert_eq!(
             md,
-            "|Minor1|Minor2|Minor3|Minor4|\n|||||\n| col1 | col2 | col3 | col4 |"
+            "|Minor1|Minor2|Minor3|Minor4|\n|-----:|:----:|-----:|:-----|\n| col1 | col2 | col3 | col4 |"
+        );
+    }
+
+    #[test]
+    fn test_tables_alignment_col() {
+        let s = r#"<table>
+  <colgroup><col><col style="text-align: right"></colgroup>
+  <colgroup align="center" span="2"></colgroup>
+  <tr><th align="left">Item</th><th>Price</th><th>Qty</th><th style="text-align:right">Total</th></tr>
+  <tr><td>Tea</td><td>2.50</td><td>4</td><td>10.00</td></tr>
+</table>"#;
+
+        // both backends write the delimiter as wide as the column, the rewriter columns are 3 wide
+        let m = "|Item|Price|Qty|Total|\n|:--|--:|:-:|--:|\n|Tea|2.50|4|10.00|";
+
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+
+        let md = parse_html(s, false);
+        assert_eq!(
+            md,
+            "|Item|Price|Qty|Total|\n|:---|----:|:-:|----:|\n|Tea |2.50 | 4 |10.00|"
         );
     }
 
@@ -209,6 +310,6 @@ pub mod test {
             false,
         );
 
-        assert_eq!(md, "| One ring | Patterns | Titanic | | | |\n|||||||\n| One ring to rule them all |There's one for the sorrow| Roll on, Titanic, roll | | | |\n| One ring to find them | And two for the joy |You're the pride of White Star Line| | | |\n| One ring to bring them all | And three for the girls | Roll on, Titanic, roll | | | |\n|And in the darkness bind them| And four for the boys | Into the mists of time | | | |");
+        assert_eq!(md, "| One ring | Patterns | Titanic | | | |\n|-----------------------------|--------------------------|-----------------------------------|---|---|---|\n| One ring to rule them all |There's one for the sorrow| Roll on, Titanic, roll | | | |\n| One ring to find them | And two for the joy |You're the pride of White Star Line| | | |\n| One ring to bring them all | And three for the girls | Roll on, Titanic, roll | | | |\n|And in the darkness bind them| And four for the boys | Into the mists of time | | | |");
     }
 }
@@ -1,6 +1,10 @@
 #[cfg(feature = "scraper")]
 pub mod test {
-    use html2md::{parse_html, rewrite_html};
+    use html2md::{
+        parse_html, parse_html_with_options, rewrite_html, rewrite_html_with_options, BreakMarker,
+        BulletMarker, ConversionOptions, EmphasisMarker, FenceMarker, HeadingStyle, LinkStyle,
+        MarkdownStyle,
+    };
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -201,5 +205,375 @@ pub mod test {
         let html = r#"<p>non&nbsp;breaking</p>"#;
         let md = rewrite_html(html, false);
         assert_eq!(md, "nonbreaking");
+
+        // the decoded `&` does not start a reference with the text after it
+        let html = r#"<p>&amp;lt; &amp;#91; AT&amp;T</p>"#;
+        let md = rewrite_html(html, false);
+        assert_eq!(md, "\\&lt; \\&#91; AT&T");
+    }
+
+    #[test]
+    fn test_conversion_options() {
+        let s = r#"<nav>menu</nav><p><a href="/docs">Docs</a> X<sub>2</sub></p>"#;
+        let options = ConversionOptions::default()
+            .with_commonmark(true)
+            .with_url(url::Url::parse("https://example.com").ok())
+            .with_ignore(["nav".to_string()].into_iter().collect());
+        let m = "[Docs](https://example.com/docs) X<sub>2</sub>";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_conversion_options_ignore_selector() {
+        let s = r#"<div class="ad">Buy now</div><p>Content</p>"#;
+        let options =
+            ConversionOptions::default().with_ignore([".ad".to_string()].into_iter().collect());
+
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, "Content");
+    }
+
+    #[test]
+    fn test_relative_urls() {
+        let s = r##"<p><a href="page.html">Page</a> <a href="?page=2">Next</a> <a href="#top">Top</a> <img src="../img/a.png" alt="A"> <a href="//cdn.example.com/x.js">CDN</a> <a href="mailto:a@example.com">Mail</a></p>"##;
+        let options = ConversionOptions::default()
+            .with_url(url::Url::parse("https://example.com/docs/intro.html").ok());
+        let m = "[Page](https://example.com/docs/page.html) [Next](https://example.com/docs/intro.html?page=2) [Top](#top) ![A](https://example.com/img/a.png) [CDN](https://cdn.example.com/x.js) [Mail](mailto:a@example.com)";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_relative_srcset() {
+        let s = r#"<img src="a.png" srcset="a.png 1x, /b.png 2x" width="10">"#;
+        let options = ConversionOptions::default()
+            .with_commonmark(true)
+            .with_url(url::Url::parse("https://example.com/docs/").ok());
+        let m = r#"<img src="https://example.com/docs/a.png" srcset="https://example.com/docs/a.png 1x, https://example.com/b.png 2x" width="10" />"#;
+
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_base_href() {
+        let s = r#"<html><head><base href="/docs/"></head><body><a href="page.html">Page</a> <img src="a.png" alt="A"></body></html>"#;
+        let options = ConversionOptions::default()
+            .with_url(url::Url::parse("https://example.com/blog/").ok());
+        let m = "[Page](https://example.com/docs/page.html) ![A](https://example.com/docs/a.png)";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+
+        let s = r#"<html><head><base href="https://cdn.example.com/"></head><body><a href="x">X</a></body></html>"#;
+        let m = "[X](https://cdn.example.com/x)";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_reference_links() {
+        let s = r#"<p>See <a href="/a">A</a>, <a href="/b">B</a> and <a href="/a">A again</a>.</p><p><a href="/c d">C</a></p>"#;
+        let options = ConversionOptions::default()
+            .with_url(url::Url::parse("https://example.com").ok())
+            .with_link_style(LinkStyle::Referenced);
+        let m = "See [A][1], [B][2] and [A again][1].\n[C][3]\n\n[1]: https://example.com/a\n[2]: https://example.com/b\n[3]: https://example.com/c%20d";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_links_extraction_cross_origin_base() {
+        use html2md::rewrite_html_with_links;
+
+        let s = r#"<html><head><base href="https://cdn.example.net/"></head><body><a href="https://example.com/about">About</a> <a href="file.zip">File</a></body></html>"#;
+        let options =
+            ConversionOptions::default().with_url(url::Url::parse("https://example.com/").ok());
+
+        let output = rewrite_html_with_links(s, &options);
+        let links: Vec<_> = output
+            .links
+            .iter()
+            .map(|link| (link.url.as_str(), link.internal))
+            .collect();
+
+        assert_eq!(
+            links,
+            vec![
+                ("https://example.com/about", true),
+                ("https://cdn.example.net/file.zip", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_extraction() {
+        use html2md::{rewrite_html_with_links, ExtractedImage, ExtractedLink};
+
+        let s = r#"<p><a href="/docs" title="Docs">Read  the <b>docs</b></a> or <a href="https://other.com/x" rel="nofollow noopener">Other &amp; more</a></p><img src="logo.png" alt="Logo">"#;
+        let options = ConversionOptions::default()
+            .with_url(url::Url::parse("https://example.com/blog/").ok());
+
+        let output = rewrite_html_with_links(s, &options);
+
+        assert_eq!(output.markdown, rewrite_html_with_options(s, &options));
+        assert_eq!(
+            output.links,
+            vec![
+                ExtractedLink {
+                    url: "https://example.com/docs".into(),
+                    text: "Read the docs".into(),
+                    title: Some("Docs".into()),
+                    rel: vec![],
+                    internal: true,
+                },
+                ExtractedLink {
+                    url: "https://other.com/x".into(),
+                    text: "Other & more".into(),
+                    title: None,
+                    rel: vec!["nofollow".into(), "noopener".into()],
+                    internal: false,
+                },
+            ]
+        );
+        assert_eq!(
+            output.images,
+            vec![ExtractedImage {
+                url: "https://example.com/blog/logo.png".into(),
+                alt: "Logo".into(),
+                title: None,
+                internal: true,
+            }]
+        );
+
+        // the character references of the attributes are decoded
+        let s = r#"<a href="/a?x=1&amp;y=2" title="A &amp; B">a</a><img src="/i.png?w=1&amp;h=2" alt="&lt;i&gt;">"#;
+        let output = rewrite_html_with_links(s, &options);
+
+        assert_eq!(
+            output.markdown,
+            "[a](https://example.com/a?x=1&y=2)![&lt;i&gt;](https://example.com/i.png?w=1&h=2)"
+        );
+        assert_eq!(output.links[0].url, "https://example.com/a?x=1&y=2");
+        assert_eq!(output.links[0].title.as_deref(), Some("A & B"));
+        assert_eq!(output.images[0].url, "https://example.com/i.png?w=1&h=2");
+        assert_eq!(output.images[0].alt, "<i>");
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let s = r#"<html lang="en"><head><title>Hello &amp;  "World"</title><meta name="description" content="A  page"><link rel="canonical" href="/post"><meta property="og:title" content="OG"><meta name="twitter:card" content="summary"></head><body><h1>Hi</h1><p>x <svg><title>icon</title></svg></p></body></html>"#;
+        let options = ConversionOptions::default()
+            .with_url(url::Url::parse("https://example.com/blog/").ok())
+            .with_front_matter(true);
+        let m = "---\ntitle: \"Hello & \\\"World\\\"\"\ndescription: \"A page\"\nlang: \"en\"\ncanonical: \"https://example.com/post\"\nog:title: \"OG\"\ntwitter:card: \"summary\"\n---\n\n";
+
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, format!("{m}# Hi\nx icon"));
+
+        let md = rewrite_html_with_options("<p>Hi</p>", &options);
+        assert_eq!(md, "Hi");
+
+        let s = "<title>T</title><h1>Hi</h1><svg><title>icon</title></svg>";
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, "---\ntitle: \"T\"\n---\n\n# Hi\nicon");
+    }
+
+    #[test]
+    fn test_main_content() {
+        let s = r#"<body><nav><a href="/a">A</a> <a href="/b">B</a></nav>
+<div class="layout"><div class="post-body"><h1>The article</h1>
+<p>This is the first paragraph of the article, with enough text, commas, and words to be scored.</p>
+<p>The second paragraph continues the story, with a <a href="/x">link</a> inside.</p>
+<div class="share-buttons"><a href="/tw">Tweet</a> <a href="/fb">Share</a></div></div>
+<div class="related-articles"><ul><li><a href="/r1">Some related story about other things</a></li><li><a href="/r2">Another related story</a></li></ul></div>
+<div id="comments"><p>First comment, this is a long comment with a lot of words, commas, and opinions.</p></div>
+<div class="sidebar"><p>Sidebar text about the author, the site, and the newsletter.</p></div></div>
+<footer>Copyright</footer></body>"#;
+        let options = ConversionOptions::default().with_main_content(true);
+        let m = "# The article\nThis is the first paragraph of the article, with enough text, commas, and words to be scored.\nThe second paragraph continues the story, with a [link](/x) inside.";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+
+        // without enough text the whole document is converted
+        let md = parse_html_with_options(r#"<div class="sidebar">Menu</div><p>Hi</p>"#, &options);
+        assert_eq!(md, "Menu\nHi");
+    }
+
+    #[test]
+    fn test_include_selectors() {
+        let s = r#"<body><h1>Site</h1><ul><li>menu</li></ul><article><h2>Title</h2><p>Body <a href="/x">link</a></p></article><p>between</p><div id="content"><p>More <b>bold</b></p></div><div class="a post-body">Post</div><hr><p>end</p></body>"#;
+        let options = ConversionOptions::default().with_include(
+            [
+                "article".to_string(),
+                "#content".into(),
+                ".post-body".into(),
+            ]
+            .into(),
+        );
+        let m = "## Title\nBody [link](/x)\nMore **bold**\nPost";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+
+        let options = ConversionOptions::default().with_include(["main".to_string()].into());
+        assert_eq!(rewrite_html_with_options(s, &options), "");
+
+        // a void element and a cell without its table
+        let s = r#"<p>x <img class="hero" src="/a.png" alt="A"> y</p><table><tr><td>a</td><td class="k">b</td></tr></table>"#;
+        let options =
+            ConversionOptions::default().with_include(["img.hero".to_string(), ".k".into()].into());
+        let m = "![A](/a.png)b";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+
+        // an invalid selector is dropped
+        let options =
+            ConversionOptions::default().with_include(["[[".to_string(), ".k".into()].into());
+        assert_eq!(rewrite_html_with_options(s, &options), "b");
+
+        let options = ConversionOptions::default().with_include(["[[".to_string()].into());
+        assert_eq!(
+            rewrite_html_with_options(s, &options),
+            rewrite_html_with_options(s, &ConversionOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_custom_handlers() {
+        use html2md::CustomElement;
+
+        let s = r#"<p>Intro</p><x-card title="Card"><p>Body</p></x-card><div class="ad">Buy</div><p>See <a href="/x" data-external="1">this</a> <abbr title="HyperText">HTML</abbr></p>"#;
+        let options = ConversionOptions::default()
+            .with_custom_handler("x-card", |el: &mut CustomElement| {
+                let title = el.get_attribute("title").unwrap_or_default();
+                el.before(&format!("\n**{title}**\n"));
+            })
+            .with_custom_handler(".ad", |el: &mut CustomElement| el.replace("[ad]"))
+            .with_custom_handler("a[data-external]", |el: &mut CustomElement| {
+                el.after(" (external)")
+            })
+            .with_custom_handler("abbr", |el: &mut CustomElement| {
+                let title = el.get_attribute("title").unwrap_or_default().to_string();
+                el.after(&format!(" ({title})"));
+            });
+        let m = "Intro\n**Card**\nBody\n[ad]\nSee [this](/x) (external) HTML (HyperText)";
+
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+
+        // the markdown is written verbatim and an invalid selector is never matched
+        let options = ConversionOptions::default()
+            .with_custom_handler("[[", |el: &mut CustomElement| el.replace("never"))
+            .with_custom_handler("kbd", |el: &mut CustomElement| {
+                el.replace("<kbd>Ctrl</kbd> & *C*")
+            });
+        let md = rewrite_html_with_options("<p>Press <kbd>ctrl c</kbd></p>", &options);
+        assert_eq!(md, "Press <kbd>Ctrl</kbd> & *C*");
+    }
+
+    #[test]
+    fn test_markdown_style() {
+        let s = r#"<h1>Title</h1><h2>Part</h2><h3>Section</h3>
+<p>A <strong>bold</strong> and <em>light</em> word.</p>
+<ul><li>One</li><li>Two</li></ul>
+<hr>
+<pre><code class="language-sh">echo ~~~</code></pre>"#;
+
+        let style = MarkdownStyle::default()
+            .with_bullet(BulletMarker::Dash)
+            .with_emphasis(EmphasisMarker::Underscore)
+            .with_strong(EmphasisMarker::Underscore)
+            .with_heading(HeadingStyle::Setext)
+            .with_thematic_break(BreakMarker::Asterisk)
+            .with_code_fence(FenceMarker::Tilde);
+        let options = ConversionOptions::default().with_style(style);
+
+        let m = "Title\n===\n\nPart\n---\n### Section\nA __bold__ and _light_ word.\n- One\n- Two\n\n***\n\n~~~~sh\necho ~~~\n~~~~";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+        // an empty heading has no underline
+        let s = r#"<h1></h1><p>a</p><h2> </h2><h2>T</h2><h1><img src="/x.png"></h1>"#;
+        let m = "a\n\nT\n---\n\n![](/x.png)\n===";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_thematic_break() {
+        // without the blank lines the break would underline the paragraph as a heading
+        let s = "<hr><p>a</p><hr><hr><ul><li>b</li></ul><hr>";
+        let m = "---\n\na\n\n---\n\n---\n\n* b\n\n---";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_wrap_width() {
+        let s = r#"<p>The quick brown fox reads <a href="https://example.com/a/long/path">a linked page</a> and <code>an inline span</code>, pays 10 dollars - or 1. more.</p>
+<ul><li>A list item long enough to wrap</li></ul>
+<pre><code>a code line that is never wrapped</code></pre>"#;
+
+        let options = ConversionOptions::default().with_wrap_width(Some(24));
+
+        let m = "The quick brown fox\nreads [a linked\npage](https://example.com/a/long/path)\nand `an inline span`,\npays 10 dollars - or 1.\nmore.\n* A list item long\n  enough to wrap\n```\na code line that is never wrapped\n```";
+
+        let md = parse_html_with_options(s, &options);
+        assert_eq!(md, m);
+        let md = rewrite_html_with_options(s, &options);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_line_start_escaping() {
+        let s = r#"<p>1. Not a list</p><h2># not a heading</h2><p>- dash, + plus and
+= sign</p><ul><li>2) item</li></ul><p>| a | b |</p><p>```js</p><p>Pay <b>1.</b> - not 2. here</p>"#;
+
+        let m = "1\\. Not a list\n## \\# not a heading\n\\- dash, + plus and\n\\= sign\n* 2\\) item\n\\| a | b |\n\\```js\nPay **1.** - not 2. here";
+
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
+    }
+
+    #[test]
+    fn test_html_entities() {
+        let s = r#"<p>&copy; 2024 &hellip; caf&eacute; &#8364;5 &#x1F600; &#150; &ast;a&lowbar; &lt;b&gt; &NotEqualTilde; &bogus;</p><pre><code>a &lt; b &rarr; c</code></pre>"#;
+
+        let m = "© 2024 … café €5 😀 – \\*a\\_ \\<b\\> ≂̸ &bogus;\n```\na < b → c\n```";
+
+        let md = parse_html(s, false);
+        assert_eq!(md, m);
+        let md = rewrite_html(s, false);
+        assert_eq!(md, m);
     }
 }